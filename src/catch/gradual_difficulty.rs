@@ -98,6 +98,11 @@ impl<'map> CatchGradualDifficultyAttributes<'map> {
         }
     }
 
+    /// Count the fruits and droplets that are yet to be processed.
+    pub(crate) fn count_remaining(&self) -> usize {
+        self.hit_objects.clone().count()
+    }
+
     fn init_hyper_dash(&mut self, next: &CatchObject) {
         self.prev.init_hyper_dash(
             self.half_catcher_width,
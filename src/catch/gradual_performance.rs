@@ -1,12 +1,18 @@
+use std::convert::TryFrom;
+
 use crate::{Beatmap, CatchPP};
 
-use super::{CatchGradualDifficultyAttributes, CatchPerformanceAttributes};
+use super::{
+    CatchDifficultyAttributes, CatchGradualDifficultyAttributes, CatchPerformanceAttributes,
+    CatchScoreStateError,
+};
 
 /// Aggregation for a score's current state i.e. what was the
 /// maximum combo so far and what are the current hitresults.
 ///
 /// This struct is used for [`CatchGradualPerformanceAttributes`].
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CatchScoreState {
     /// Maximum combo that the score has had so far.
     /// **Not** the maximum possible combo of the map so far.
@@ -32,6 +38,63 @@ impl CatchScoreState {
     }
 }
 
+impl From<&CatchPerformanceAttributes> for CatchScoreState {
+    #[inline]
+    fn from(attributes: &CatchPerformanceAttributes) -> Self {
+        attributes.state.clone()
+    }
+}
+
+/// Validates that the state's hitresults and combo fit the map's object composition.
+impl TryFrom<CatchPerformanceAttributes> for CatchScoreState {
+    type Error = CatchScoreStateError;
+
+    fn try_from(attributes: CatchPerformanceAttributes) -> Result<Self, Self::Error> {
+        let CatchPerformanceAttributes {
+            difficulty, state, ..
+        } = attributes;
+
+        let n_objects = difficulty.max_combo();
+        let total = state.n_fruits + state.n_droplets + state.misses;
+
+        if total > n_objects {
+            return Err(CatchScoreStateError::TooManyHitResults { total, n_objects });
+        }
+
+        if state.n_fruits > difficulty.n_fruits {
+            return Err(CatchScoreStateError::TooManyFruits {
+                n_fruits: state.n_fruits,
+                max_fruits: difficulty.n_fruits,
+            });
+        }
+
+        if state.n_droplets > difficulty.n_droplets {
+            return Err(CatchScoreStateError::TooManyDroplets {
+                n_droplets: state.n_droplets,
+                max_droplets: difficulty.n_droplets,
+            });
+        }
+
+        let total = state.n_tiny_droplets + state.n_tiny_droplet_misses;
+
+        if total > difficulty.n_tiny_droplets {
+            return Err(CatchScoreStateError::TooManyTinyDroplets {
+                total,
+                max_tiny_droplets: difficulty.n_tiny_droplets,
+            });
+        }
+
+        if state.max_combo > n_objects {
+            return Err(CatchScoreStateError::ComboTooHigh {
+                combo: state.max_combo,
+                max_combo: n_objects,
+            });
+        }
+
+        Ok(state)
+    }
+}
+
 /// Gradually calculate the performance attributes of an osu!catch map.
 ///
 /// After each hit object you can call
@@ -127,7 +190,9 @@ impl CatchScoreState {
 #[derive(Clone, Debug)]
 pub struct CatchGradualPerformanceAttributes<'map> {
     difficulty: CatchGradualDifficultyAttributes<'map>,
+    current: Option<CatchDifficultyAttributes>,
     performance: CatchPP<'map>,
+    n_objects: usize,
 }
 
 impl<'map> CatchGradualPerformanceAttributes<'map> {
@@ -135,13 +200,49 @@ impl<'map> CatchGradualPerformanceAttributes<'map> {
     pub fn new(map: &'map Beatmap, mods: u32) -> Self {
         let difficulty = CatchGradualDifficultyAttributes::new(map, mods);
         let performance = CatchPP::new(map).mods(mods).passed_objects(0);
+        let n_objects = difficulty.count_remaining();
 
         Self {
             difficulty,
+            current: None,
             performance,
+            n_objects,
         }
     }
 
+    /// Return the amount of fruits and droplets that were processed so far.
+    #[inline]
+    pub fn current_index(&self) -> usize {
+        self.difficulty.idx
+    }
+
+    /// Return the amount of fruits and droplets that remain to be processed.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.n_objects - self.difficulty.idx
+    }
+
+    /// Return the total amount of fruits and droplets, processed or not.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.n_objects
+    }
+
+    /// Return whether the map contains no fruits or droplets at all.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.n_objects == 0
+    }
+
+    /// Return the difficulty attributes of the objects that were processed so far
+    /// without processing any further, e.g. for the current star rating mid-replay.
+    ///
+    /// Returns `None` if no object was processed yet.
+    #[inline]
+    pub fn current(&self) -> Option<&CatchDifficultyAttributes> {
+        self.current.as_ref()
+    }
+
     /// Process the next hit object and calculate the
     /// performance attributes for the resulting score state.
     ///
@@ -175,6 +276,7 @@ impl<'map> CatchGradualPerformanceAttributes<'map> {
         }
 
         let difficulty = difficulty?;
+        self.current = Some(difficulty.clone());
 
         let performance = self
             .performance
@@ -193,6 +295,56 @@ mod tests {
     #[allow(unused_imports)]
     use super::*;
 
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn state_roundtrip() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+
+        let performance = CatchPP::new(&map)
+            .mods(8)
+            .combo(500)
+            .misses(2)
+            .accuracy(97.0)
+            .calculate();
+
+        let state = CatchScoreState::from(&performance);
+        assert_eq!(state.max_combo, 500);
+        assert_eq!(state.misses, 2);
+        assert_eq!(
+            state.n_fruits + state.n_droplets + state.misses,
+            performance.max_combo()
+        );
+
+        let recalculated = CatchPP::new(&map).mods(8).state(state.clone()).calculate();
+        assert_eq!(recalculated, performance);
+
+        assert_eq!(CatchScoreState::try_from(performance.clone()), Ok(state));
+
+        let mut invalid = performance.clone();
+        invalid.state.n_tiny_droplet_misses += 1;
+
+        assert_eq!(
+            CatchScoreState::try_from(invalid),
+            Err(CatchScoreStateError::TooManyTinyDroplets {
+                total: performance.difficulty.n_tiny_droplets + 1,
+                max_tiny_droplets: performance.difficulty.n_tiny_droplets,
+            })
+        );
+
+        let mut invalid = performance.clone();
+        invalid.state.n_droplets = performance.difficulty.n_droplets + 1;
+        invalid.state.n_fruits =
+            performance.max_combo() - invalid.state.n_droplets - invalid.state.misses;
+
+        assert_eq!(
+            CatchScoreState::try_from(invalid),
+            Err(CatchScoreStateError::TooManyDroplets {
+                n_droplets: performance.difficulty.n_droplets + 1,
+                max_droplets: performance.difficulty.n_droplets,
+            })
+        );
+    }
+
     #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
     #[test]
     fn correct_empty() {
@@ -244,6 +396,74 @@ mod tests {
         assert_eq!(next_n, next);
     }
 
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn current_index() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+        let mods = 64;
+        let state = CatchScoreState::default();
+
+        let mut gradual = CatchGradualPerformanceAttributes::new(&map, mods);
+        assert_eq!(gradual.current_index(), 0);
+
+        for _ in 0..10 {
+            let _ = gradual.process_next_object(state.clone());
+        }
+
+        assert_eq!(gradual.current_index(), 10);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn remaining() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+        let mods = 64;
+        let state = CatchScoreState::default();
+
+        let mut gradual = CatchGradualPerformanceAttributes::new(&map, mods);
+        let total = gradual.remaining();
+
+        assert!(total > 10);
+        assert_eq!(gradual.len(), total);
+
+        for i in 1..=10 {
+            let _ = gradual.process_next_object(state.clone());
+            assert_eq!(gradual.remaining(), total - i);
+            assert_eq!(gradual.current_index() + gradual.remaining(), gradual.len());
+        }
+
+        let _ = gradual.process_next_n_objects(state.clone(), 100);
+        assert_eq!(gradual.remaining(), total - 110);
+
+        let _ = gradual.process_next_n_objects(state.clone(), usize::MAX);
+        assert_eq!(gradual.remaining(), 0);
+        assert_eq!(gradual.len(), total);
+        assert!(!gradual.is_empty());
+        assert!(gradual.process_next_object(state).is_none());
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn current() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+        let mods = 64;
+        let state = CatchScoreState::default();
+
+        let mut gradual = CatchGradualPerformanceAttributes::new(&map, mods);
+        assert!(gradual.current().is_none());
+
+        let _ = gradual.process_next_n_objects(state.clone(), 25);
+        let expected = CatchGradualDifficultyAttributes::new(&map, mods).nth(24);
+        assert_eq!(gradual.current(), expected.as_ref());
+
+        // Peeking doesn't advance
+        assert_eq!(gradual.current(), expected.as_ref());
+        assert_eq!(gradual.current_index(), 25);
+
+        let performance = gradual.process_next_object(state).unwrap();
+        assert_eq!(gradual.current(), Some(&performance.difficulty));
+    }
+
     #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
     #[test]
     fn gradual_end_eq_regular() {
@@ -5,6 +5,7 @@ mod gradual_difficulty;
 mod gradual_performance;
 mod movement;
 mod pp;
+mod score_state_error;
 mod slider_state;
 
 use catch_object::CatchObject;
@@ -14,9 +15,13 @@ pub use gradual_difficulty::*;
 pub use gradual_performance::*;
 use movement::Movement;
 pub use pp::*;
+pub use score_state_error::CatchScoreStateError;
 use slider_state::SliderState;
 
-use crate::{catch::fruit_or_juice::FruitParams, curve::CurveBuffers, Beatmap, Mods, Strains};
+use crate::{
+    catch::fruit_or_juice::FruitParams, curve::CurveBuffers, Beatmap, BeatmapAttributes, Mods,
+    Strains,
+};
 
 const SECTION_LENGTH: f64 = 750.0;
 const STAR_SCALING_FACTOR: f64 = 0.153;
@@ -130,10 +135,11 @@ fn calculate_movement(params: CatchStars<'_>) -> (Movement, CatchDifficultyAttri
     let take = passed_objects.unwrap_or(usize::MAX);
 
     let map_attributes = map.attributes().mods(mods);
+    let ar = catch_ar(map, mods, clock_rate);
     let clock_rate = clock_rate.unwrap_or(map_attributes.clock_rate);
 
     let attributes = CatchDifficultyAttributes {
-        ar: map_attributes.ar,
+        ar,
         ..Default::default()
     };
 
@@ -214,6 +220,16 @@ fn calculate_movement(params: CatchStars<'_>) -> (Movement, CatchDifficultyAttri
     (movement, params.attributes)
 }
 
+/// The AR w.r.t. mods and, if specified, a custom clock rate.
+pub(crate) fn catch_ar(map: &Beatmap, mods: u32, clock_rate: Option<f64>) -> f64 {
+    match clock_rate {
+        Some(clock_rate) => {
+            BeatmapAttributes::clock_rate_ar(map.ar as f64 * mods.od_ar_hp_multiplier(), clock_rate)
+        }
+        None => map.attributes().mods(mods).ar,
+    }
+}
+
 #[inline]
 pub(crate) fn calculate_catch_width(cs: f32) -> f32 {
     let scale = 1.0 - 0.7 * (cs - 5.0) / 5.0;
@@ -223,6 +239,7 @@ pub(crate) fn calculate_catch_width(cs: f32) -> f32 {
 
 /// The result of a difficulty calculation on an osu!catch map.
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CatchDifficultyAttributes {
     /// The final star rating
     pub stars: f64,
@@ -246,11 +263,14 @@ impl CatchDifficultyAttributes {
 
 /// The result of a performance calculation on an osu!catch map.
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CatchPerformanceAttributes {
     /// The difficulty attributes that were used for the performance calculation
     pub difficulty: CatchDifficultyAttributes,
     /// The final performance points.
     pub pp: f64,
+    /// The score state that the calculation resolved to.
+    pub state: CatchScoreState,
 }
 
 impl CatchPerformanceAttributes {
@@ -278,3 +298,54 @@ impl From<CatchPerformanceAttributes> for CatchDifficultyAttributes {
         attributes.difficulty
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(all(
+        feature = "serde",
+        not(any(feature = "async_tokio", feature = "async_std"))
+    ))]
+    #[test]
+    fn serde_roundtrip() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+        let performance = CatchPP::new(&map)
+            .mods(8)
+            .misses(2)
+            .accuracy(97.5)
+            .calculate();
+
+        let json = serde_json::to_string(&performance).unwrap();
+        let deserialized: CatchPerformanceAttributes = serde_json::from_str(&json).unwrap();
+        assert_eq!(performance, deserialized);
+
+        let json = serde_json::to_string(&performance.difficulty).unwrap();
+        let attributes: CatchDifficultyAttributes = serde_json::from_str(&json).unwrap();
+
+        let recalculated = CatchPP::new(&map)
+            .attributes(attributes)
+            .mods(8)
+            .misses(2)
+            .accuracy(97.5)
+            .calculate();
+
+        assert_eq!(performance, recalculated);
+
+        let state = CatchScoreState {
+            max_combo: 500,
+            n_fruits: 480,
+            n_droplets: 2,
+            n_tiny_droplets: 250,
+            n_tiny_droplet_misses: 3,
+            misses: 2,
+        };
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(
+            state,
+            serde_json::from_str::<CatchScoreState>(&json).unwrap()
+        );
+    }
+}
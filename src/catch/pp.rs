@@ -1,4 +1,6 @@
-use super::{CatchDifficultyAttributes, CatchPerformanceAttributes, CatchScoreState, CatchStars};
+use super::{
+    catch_ar, CatchDifficultyAttributes, CatchPerformanceAttributes, CatchScoreState, CatchStars,
+};
 use crate::{Beatmap, DifficultyAttributes, Mods, PerformanceAttributes};
 
 /// Performance calculator on osu!catch maps.
@@ -45,6 +47,7 @@ pub struct CatchPP<'map> {
     pub(crate) n_misses: usize,
     passed_objects: Option<usize>,
     clock_rate: Option<f64>,
+    tiny_droplet_weight: f64,
 }
 
 impl<'map> CatchPP<'map> {
@@ -64,9 +67,29 @@ impl<'map> CatchPP<'map> {
             n_misses: 0,
             passed_objects: None,
             clock_rate: None,
+            tiny_droplet_weight: 1.0,
         }
     }
 
+    /// Calculate the performance of a perfect play on the given map with the given mods,
+    /// i.e. all fruits, droplets, and tiny droplets caught.
+    ///
+    /// The difficulty attributes are calculated only once.
+    pub fn max_pp(map: &'map Beatmap, mods: u32) -> CatchPerformanceAttributes {
+        let attributes = CatchStars::new(map).mods(mods).calculate();
+
+        CatchPP::new(map)
+            .mods(mods)
+            .combo(attributes.max_combo())
+            .fruits(attributes.n_fruits)
+            .droplets(attributes.n_droplets)
+            .tiny_droplets(attributes.n_tiny_droplets)
+            .tiny_droplet_misses(0)
+            .misses(0)
+            .attributes(attributes)
+            .calculate()
+    }
+
     /// Provide the result of a previous difficulty or performance calculation.
     /// If you already calculated the attributes for the current map-mod combination,
     /// be sure to put them in here so that they don't have to be recalculated.
@@ -152,6 +175,9 @@ impl<'map> CatchPP<'map> {
     /// Adjust the clock rate used in the calculation.
     /// If none is specified, it will take the clock rate based on the mods
     /// i.e. 1.5 for DT, 0.75 for HT and 1.0 otherwise.
+    ///
+    /// The AR used for the pp is adjusted to the clock rate as well. Attributes
+    /// whose AR was calculated for a different clock rate are recalculated.
     #[inline]
     pub fn clock_rate(mut self, clock_rate: f64) -> Self {
         self.clock_rate = Some(clock_rate);
@@ -159,6 +185,18 @@ impl<'map> CatchPP<'map> {
         self
     }
 
+    /// Adjust how much tiny droplets and their misses weigh into the accuracy
+    /// that is used to scale the pp.
+    ///
+    /// A weight of `1.0` counts them like fruits and droplets which is the default,
+    /// a weight of `0.0` ignores them entirely.
+    #[inline]
+    pub fn tiny_droplet_weight(mut self, weight: f64) -> Self {
+        self.tiny_droplet_weight = weight.max(0.0);
+
+        self
+    }
+
     /// Provide parameters through an [`CatchScoreState`].
     #[inline]
     pub fn state(mut self, state: CatchScoreState) -> Self {
@@ -184,6 +222,11 @@ impl<'map> CatchPP<'map> {
     /// Generate the hit results with respect to the given accuracy between `0` and `100`.
     ///
     /// Be sure to set `misses` beforehand! Also, if available, set `attributes` beforehand.
+    ///
+    /// Only tiny droplets are adjusted so the resulting accuracy generally lies within
+    /// a fraction of a percent of the target. If the target can't be reached, e.g. because
+    /// the misses alone already lower the accuracy too much or because there are too few
+    /// tiny droplets, the closest accuracy is used instead.
     pub fn accuracy(mut self, mut acc: f64) -> Self {
         if self.attributes.is_none() {
             let mut calculator = CatchStars::new(self.map).mods(self.mods);
@@ -220,6 +263,7 @@ impl<'map> CatchPP<'map> {
             ((acc * (max_combo + max_tiny_droplets) as f64).round() as usize)
                 .saturating_sub(n_fruits)
                 .saturating_sub(n_droplets)
+                .min(max_tiny_droplets)
         });
 
         let n_tiny_droplet_misses = max_tiny_droplets.saturating_sub(n_tiny_droplets);
@@ -288,6 +332,7 @@ impl<'map> CatchPP<'map> {
                 n_tiny_droplets,
                 n_tiny_droplet_misses,
                 n_misses: self.n_misses,
+                tiny_droplet_weight: self.tiny_droplet_weight,
             };
         }
 
@@ -300,27 +345,54 @@ impl<'map> CatchPP<'map> {
             n_tiny_droplets: self.n_tiny_droplets.unwrap_or(0),
             n_tiny_droplet_misses: self.n_tiny_droplet_misses.unwrap_or(0),
             n_misses: self.n_misses,
+            tiny_droplet_weight: self.tiny_droplet_weight,
         }
     }
 
     /// Calculate all performance related values, including pp and stars.
     pub fn calculate(mut self) -> CatchPerformanceAttributes {
-        let attributes = self.attributes.take().unwrap_or_else(|| {
-            let mut calculator = CatchStars::new(self.map).mods(self.mods);
+        let attributes = self.attributes.take();
 
-            if let Some(passed_objects) = self.passed_objects {
-                calculator = calculator.passed_objects(passed_objects);
-            }
+        let attributes = attributes
+            .filter(|attributes| !self.mismatches_clock_rate(attributes))
+            .unwrap_or_else(|| {
+                let mut calculator = CatchStars::new(self.map).mods(self.mods);
 
-            if let Some(clock_rate) = self.clock_rate {
-                calculator = calculator.clock_rate(clock_rate);
-            }
+                if let Some(passed_objects) = self.passed_objects {
+                    calculator = calculator.passed_objects(passed_objects);
+                }
 
-            calculator.calculate()
-        });
+                if let Some(clock_rate) = self.clock_rate {
+                    calculator = calculator.clock_rate(clock_rate);
+                }
+
+                calculator.calculate()
+            });
 
         self.assert_hitresults(attributes).calculate()
     }
+
+    /// Calculate all performance related values and additionally return
+    /// the difficulty attributes separately.
+    #[inline]
+    pub fn calculate_all(self) -> (CatchDifficultyAttributes, CatchPerformanceAttributes) {
+        let performance = self.calculate();
+
+        (performance.difficulty.clone(), performance)
+    }
+
+    /// Whether the attributes' AR was calculated for a different clock rate
+    /// than the one that was specified.
+    fn mismatches_clock_rate(&self, attributes: &CatchDifficultyAttributes) -> bool {
+        // Without hit objects there is nothing to recalculate from
+        if self.clock_rate.is_none() || self.map.hit_objects.is_empty() {
+            return false;
+        }
+
+        let ar = catch_ar(self.map, self.mods, self.clock_rate);
+
+        (attributes.ar - ar).abs() > 1e-6
+    }
 }
 
 struct CatchPPInner {
@@ -332,6 +404,7 @@ struct CatchPPInner {
     n_tiny_droplets: usize,
     n_tiny_droplet_misses: usize,
     n_misses: usize,
+    tiny_droplet_weight: f64,
 }
 
 impl CatchPPInner {
@@ -357,7 +430,9 @@ impl CatchPPInner {
         pp *= len_bonus;
 
         // Penalize misses exponentially
-        pp *= 0.97_f64.powi(self.n_misses as i32);
+        let effective_misses =
+            calculate_effective_misses(attributes, self.combo, self.n_misses, combo_hits);
+        pp *= 0.97_f64.powf(effective_misses);
 
         // Combo scaling
         if let Some(combo) = self.combo.filter(|_| max_combo > 0) {
@@ -396,9 +471,19 @@ impl CatchPPInner {
             pp *= 0.9;
         }
 
+        let state = CatchScoreState {
+            max_combo: self.combo.unwrap_or(max_combo),
+            n_fruits: self.n_fruits,
+            n_droplets: self.n_droplets,
+            n_tiny_droplets: self.n_tiny_droplets,
+            n_tiny_droplet_misses: self.n_tiny_droplet_misses,
+            misses: self.n_misses,
+        };
+
         CatchPerformanceAttributes {
             difficulty: self.attributes,
             pp,
+            state,
         }
     }
 
@@ -408,29 +493,55 @@ impl CatchPPInner {
     }
 
     #[inline]
-    fn successful_hits(&self) -> usize {
-        self.n_fruits + self.n_droplets + self.n_tiny_droplets
+    fn successful_hits(&self) -> f64 {
+        (self.n_fruits + self.n_droplets) as f64
+            + self.n_tiny_droplets as f64 * self.tiny_droplet_weight
     }
 
     #[inline]
-    fn total_hits(&self) -> usize {
-        self.successful_hits() + self.n_tiny_droplet_misses + self.n_misses
+    fn total_hits(&self) -> f64 {
+        self.successful_hits()
+            + self.n_misses as f64
+            + self.n_tiny_droplet_misses as f64 * self.tiny_droplet_weight
     }
 
     #[inline]
     fn acc(&self) -> f64 {
         let total_hits = self.total_hits();
 
-        if total_hits == 0 {
+        if total_hits <= f64::EPSILON {
             1.0
         } else {
-            (self.successful_hits() as f64 / total_hits as f64)
-                .max(0.0)
-                .min(1.0)
+            (self.successful_hits() / total_hits).max(0.0).min(1.0)
         }
     }
 }
 
+fn calculate_effective_misses(
+    attributes: &CatchDifficultyAttributes,
+    combo: Option<usize>,
+    n_misses: usize,
+    combo_hits: usize,
+) -> f64 {
+    // Guess the number of combo breaks from the combo since a combo well
+    // below the max combo indicates that the score choked somewhere.
+    // Some slack is given for dropped droplets close to a full combo.
+    let full_combo_threshold = attributes.max_combo() as f64 - 0.1 * attributes.n_droplets as f64;
+
+    let combo_based_misses = match combo {
+        Some(combo) if (combo as f64) < full_combo_threshold => {
+            full_combo_threshold / combo.max(1) as f64
+        }
+        _ => 0.0,
+    };
+
+    // Clamp the misses since they're derived from combo and
+    // could be higher than the amount of hits
+    combo_based_misses
+        .min(combo_hits as f64)
+        .max(n_misses as f64)
+}
+
 /// Abstract type to provide flexibility when passing difficulty attributes to a performance calculation.
 pub trait CatchAttributeProvider {
     /// Provide the actual difficulty attributes.
@@ -489,6 +600,117 @@ mod test {
         }
     }
 
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn fruits_custom_clock_rate() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+
+        let nomod = CatchPP::new(&map).calculate();
+        let faster = CatchPP::new(&map).clock_rate(1.3).calculate();
+
+        assert!(faster.difficulty.ar > nomod.difficulty.ar);
+        assert!(faster.pp > nomod.pp);
+
+        // Attributes of a different clock rate are recalculated
+        let reused = CatchPP::new(&map)
+            .attributes(nomod.difficulty.clone())
+            .clock_rate(1.3)
+            .calculate();
+
+        assert_eq!(reused, faster);
+
+        let reused = CatchPP::new(&map)
+            .attributes(faster.difficulty.clone())
+            .clock_rate(1.3)
+            .calculate();
+
+        assert_eq!(reused, faster);
+
+        // A custom clock rate of 1.5 matches DT
+        let custom = CatchPP::new(&map).clock_rate(1.5).calculate();
+        let dt = CatchPP::new(&map).mods(64).calculate();
+
+        assert!((custom.difficulty.ar - dt.difficulty.ar).abs() < 1e-6);
+        assert!((custom.pp - dt.pp).abs() < 1e-6);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn fruits_max_pp() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+
+        for &mods in &[0, 8 + 64, 16] {
+            let max_pp = CatchPP::max_pp(&map, mods);
+            let attributes = max_pp.difficulty.clone();
+
+            assert_eq!(max_pp, CatchPP::new(&map).mods(mods).calculate());
+
+            for &(combo, misses, acc) in &[(730, 0, 99.0), (400, 1, 100.0), (100, 5, 95.0)] {
+                let pp = CatchPP::new(&map)
+                    .mods(mods)
+                    .attributes(attributes.clone())
+                    .combo(combo)
+                    .misses(misses)
+                    .accuracy(acc)
+                    .calculate()
+                    .pp;
+
+                assert!(max_pp.pp >= pp, "mods={}: {} < {}", mods, max_pp.pp, pp);
+            }
+        }
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn fruits_state_eq_individual() {
+        let map = Beatmap::from_path("./maps/2118524.osu").expect("failed to parse map");
+        let attributes = CatchStars::new(&map).mods(8).calculate();
+
+        let state = CatchScoreState {
+            max_combo: 600,
+            n_fruits: 726,
+            n_droplets: 1,
+            n_tiny_droplets: 280,
+            n_tiny_droplet_misses: 11,
+            misses: 3,
+        };
+
+        let individual = CatchPP::new(&map)
+            .mods(8)
+            .attributes(attributes.clone())
+            .combo(600)
+            .fruits(726)
+            .droplets(1)
+            .tiny_droplets(280)
+            .tiny_droplet_misses(11)
+            .misses(3)
+            .calculate();
+
+        let via_state = CatchPP::new(&map)
+            .mods(8)
+            .attributes(attributes.clone())
+            .state(state.clone())
+            .calculate();
+
+        assert_eq!(individual, via_state);
+        assert_eq!(individual.state, state);
+
+        // Only a single field, the others are filled in
+        let partial = CatchPP::new(&map)
+            .mods(8)
+            .attributes(attributes.clone())
+            .misses(2)
+            .calculate();
+
+        let via_state = CatchPP::new(&map)
+            .mods(8)
+            .attributes(attributes)
+            .state(partial.state.clone())
+            .calculate();
+
+        assert_eq!(partial.pp, via_state.pp);
+    }
+
     #[test]
     fn fruits_only_accuracy() {
         let map = Beatmap::default();
@@ -517,6 +739,126 @@ mod test {
         );
     }
 
+    #[test]
+    fn fruits_effective_misses() {
+        let attributes = CatchDifficultyAttributes {
+            n_fruits: 900,
+            n_droplets: 100,
+            ..Default::default()
+        };
+
+        let effective_misses = |combo: Option<usize>, n_misses: usize, combo_hits: usize| {
+            calculate_effective_misses(&attributes, combo, n_misses, combo_hits)
+        };
+
+        assert_eq!(effective_misses(None, 2, 1000), 2.0);
+        assert_eq!(effective_misses(Some(1000), 0, 1000), 0.0);
+        assert_eq!(effective_misses(Some(330), 1, 1000), 3.0);
+        assert_eq!(effective_misses(Some(900), 3, 1000), 3.0);
+        assert_eq!(effective_misses(Some(0), 0, 20), 20.0);
+    }
+
+    #[test]
+    fn fruits_near_fc_effective_misses() {
+        let attributes = attributes();
+        let max_combo = attributes.max_combo();
+
+        // Dropping a few droplets is within the slack of the threshold
+        for &(combo, n_misses) in &[(max_combo - 1, 1), (max_combo - 20, 2), (max_combo - 56, 1)] {
+            let effective_misses =
+                calculate_effective_misses(&attributes, Some(combo), n_misses, max_combo);
+
+            assert_eq!(effective_misses, n_misses as f64, "combo {}", combo);
+        }
+
+        let choke = calculate_effective_misses(&attributes, Some(max_combo / 2), 1, max_combo);
+        assert!(choke > 1.0);
+    }
+
+    #[test]
+    fn fruits_fc_and_late_choke() {
+        let map = Beatmap::default();
+        let attributes = CatchDifficultyAttributes {
+            stars: 5.0,
+            ar: 9.0,
+            ..attributes()
+        };
+
+        let max_combo = attributes.max_combo();
+
+        let calculate = |combo: usize, misses: usize| {
+            CatchPP::new(&map)
+                .attributes(attributes.clone())
+                .combo(combo)
+                .misses(misses)
+                .calculate()
+                .pp
+        };
+
+        let fc = calculate(max_combo, 0);
+        let late_choke = calculate(max_combo - 50, 1);
+        let early_choke = calculate(max_combo / 4, 1);
+
+        assert!(late_choke < fc);
+        assert!(early_choke < late_choke);
+
+        // Besides combo scaling, the early choke is penalized like about four misses
+        // while the late choke is close enough to a full combo to only count its miss
+        let combo_scaling = |combo: usize| (combo as f64 / max_combo as f64).powf(0.8);
+        let ratio =
+            early_choke / late_choke * combo_scaling(max_combo - 50) / combo_scaling(max_combo / 4);
+        let threshold = max_combo as f64 - 0.1 * attributes.n_droplets as f64;
+        let expected = 0.97_f64.powf(threshold / (max_combo / 4) as f64) / 0.97;
+
+        assert!((ratio - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fruits_accuracy_and_misses() {
+        let map = Beatmap::default();
+        let attributes = attributes();
+        let max_tiny_droplets = attributes.n_tiny_droplets;
+
+        let generated_acc = |target_acc: f64, n_misses: usize| {
+            let calculator = CatchPP::new(&map)
+                .attributes(attributes.clone())
+                .misses(n_misses)
+                .accuracy(target_acc);
+
+            let n_tiny_droplets = calculator.n_tiny_droplets.unwrap_or(0);
+            let numerator = calculator.n_fruits.unwrap_or(0)
+                + calculator.n_droplets.unwrap_or(0)
+                + n_tiny_droplets;
+            let denominator =
+                numerator + calculator.n_tiny_droplet_misses.unwrap_or(0) + calculator.n_misses;
+
+            assert!(n_tiny_droplets <= max_tiny_droplets);
+
+            100.0 * numerator as f64 / denominator as f64
+        };
+
+        for &(target_acc, n_misses) in &[(99.0, 5), (95.0, 20), (99.9, 3)] {
+            let acc = generated_acc(target_acc, n_misses);
+
+            assert!(
+                (target_acc - acc).abs() < 0.05,
+                "Expected: {} | Actual: {}",
+                target_acc,
+                acc
+            );
+        }
+
+        let n_objects = attributes.n_fruits + attributes.n_droplets + max_tiny_droplets;
+
+        // The misses alone prevent an SS so all tiny droplets are caught instead
+        let best = 100.0 * (n_objects - 20) as f64 / n_objects as f64;
+        assert!((generated_acc(100.0, 20) - best).abs() < f64::EPSILON);
+
+        // Fruits and droplets alone exceed the target so no tiny droplet is caught
+        let worst = 100.0 * (attributes.n_fruits + attributes.n_droplets) as f64 / n_objects as f64;
+        assert!((generated_acc(10.0, 0) - worst).abs() < f64::EPSILON);
+    }
+
     #[test]
     fn fruits_accuracy_droplets_and_tiny_droplets() {
         let map = Beatmap::default();
@@ -603,4 +945,42 @@ mod test {
             calculator.n_tiny_droplets + calculator.n_tiny_droplet_misses,
         );
     }
+
+    #[test]
+    fn fruits_tiny_droplet_weight() {
+        let map = Beatmap::default();
+        let attributes = attributes();
+
+        let total_objects = attributes.n_fruits + attributes.n_droplets;
+
+        let calculate = |n_tiny_droplet_misses: usize, weight: f64| {
+            CatchPP::new(&map)
+                .attributes(attributes.clone())
+                .passed_objects(total_objects)
+                .fruits(attributes.n_fruits)
+                .droplets(attributes.n_droplets)
+                .tiny_droplets(attributes.n_tiny_droplets - n_tiny_droplet_misses)
+                .tiny_droplet_misses(n_tiny_droplet_misses)
+                .tiny_droplet_weight(weight)
+                .calculate()
+                .pp
+        };
+
+        assert!(calculate(100, 1.0) < calculate(0, 1.0));
+        assert_eq!(calculate(100, 0.0), calculate(0, 0.0));
+    }
+
+    #[test]
+    fn fruits_calculate_all() {
+        let map = Beatmap::default();
+        let attributes = attributes();
+
+        let (difficulty, performance) = CatchPP::new(&map)
+            .attributes(attributes.clone())
+            .passed_objects(attributes.n_fruits + attributes.n_droplets)
+            .calculate_all();
+
+        assert_eq!(difficulty, attributes);
+        assert_eq!(difficulty, performance.difficulty);
+    }
 }
@@ -0,0 +1,85 @@
+use std::{error::Error as StdError, fmt};
+
+/// Anything that could be invalid about a [`CatchScoreState`](crate::catch::CatchScoreState)
+/// with respect to the map's object composition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CatchScoreStateError {
+    /// The fruits, droplets, and misses sum up to more than the amount of combo objects.
+    TooManyHitResults {
+        /// The sum of fruits, droplets, and misses.
+        total: usize,
+        /// The amount of fruits and droplets of the map.
+        n_objects: usize,
+    },
+    /// There are more caught fruits than the map contains.
+    TooManyFruits {
+        /// The amount of caught fruits.
+        n_fruits: usize,
+        /// The amount of fruits of the map.
+        max_fruits: usize,
+    },
+    /// There are more caught droplets than the map contains.
+    TooManyDroplets {
+        /// The amount of caught droplets.
+        n_droplets: usize,
+        /// The amount of droplets of the map.
+        max_droplets: usize,
+    },
+    /// Caught and missed tiny droplets sum up to more than the map contains.
+    TooManyTinyDroplets {
+        /// The sum of caught and missed tiny droplets.
+        total: usize,
+        /// The amount of tiny droplets of the map.
+        max_tiny_droplets: usize,
+    },
+    /// The combo exceeds the map's max combo.
+    ComboTooHigh {
+        /// The specified combo.
+        combo: usize,
+        /// The max combo of the map.
+        max_combo: usize,
+    },
+}
+
+impl fmt::Display for CatchScoreStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyHitResults { total, n_objects } => write!(
+                f,
+                "{} hitresults exceed the amount of fruits and droplets ({})",
+                total, n_objects
+            ),
+            Self::TooManyFruits {
+                n_fruits,
+                max_fruits,
+            } => write!(
+                f,
+                "{} fruits exceed the amount of fruits ({})",
+                n_fruits, max_fruits
+            ),
+            Self::TooManyDroplets {
+                n_droplets,
+                max_droplets,
+            } => write!(
+                f,
+                "{} droplets exceed the amount of droplets ({})",
+                n_droplets, max_droplets
+            ),
+            Self::TooManyTinyDroplets {
+                total,
+                max_tiny_droplets,
+            } => write!(
+                f,
+                "{} tiny droplets exceed the amount of tiny droplets ({})",
+                total, max_tiny_droplets
+            ),
+            Self::ComboTooHigh { combo, max_combo } => write!(
+                f,
+                "combo of {} exceeds the max combo ({})",
+                combo, max_combo
+            ),
+        }
+    }
+}
+
+impl StdError for CatchScoreStateError {}
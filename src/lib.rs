@@ -77,6 +77,12 @@
 //! println!("PP: {}", result.pp());
 //! ```
 //!
+//! ## With serde
+//! If the `serde` feature is enabled, the difficulty attributes, performance attributes,
+//! and score states of osu!standard and osu!catch implement `Serialize` and `Deserialize`
+//! so they can be cached and fed back into a calculation through `OsuPP::attributes`
+//! or `CatchPP::attributes`.
+//!
 //! ## Gradual calculation
 //! Sometimes you might want to calculate the difficulty of a map or performance of a score after each hit object.
 //! This could be done by using `passed_objects` as the amount of objects that were passed so far.
@@ -166,6 +172,8 @@
 //! | `default` | Beatmap parsing will be non-async |
 //! | `async_tokio` | Beatmap parsing will be async through [tokio](https://github.com/tokio-rs/tokio) |
 //! | `async_std` | Beatmap parsing will be async through [async-std](https://github.com/async-rs/async-std) |
+//! | `libm` | The float math of the osu!standard performance calculation will go through [libm](https://github.com/rust-lang/libm) instead of `std`. The crate itself still requires `std`, i.e. there is no `no_std` support |
+//! | `parallel` | Provides [`osu::calculate_many`] to calculate many scores in parallel through [rayon](https://github.com/rayon-rs/rayon) |
 //!
 
 #![cfg_attr(docsrs, feature(doc_cfg), deny(broken_intra_doc_links))]
@@ -204,6 +212,7 @@ mod stars;
 pub use stars::AnyStars;
 
 mod curve;
+mod math;
 mod mods;
 
 pub(crate) mod control_point_iter;
@@ -215,7 +224,7 @@ pub use mania::{ManiaPP, ManiaStars};
 pub use osu::{OsuPP, OsuStars};
 pub use taiko::{TaikoPP, TaikoStars};
 
-pub use mods::Mods;
+pub use mods::{GameMods, IntoMods, Mods, ParseModsError};
 pub use parse::{Beatmap, BeatmapAttributes, GameMode, ParseError, ParseResult};
 
 /// Provides some additional methods on [`Beatmap`](crate::Beatmap).
@@ -305,6 +314,14 @@ impl BeatmapExt for Beatmap {
     }
 }
 
+/// Calculate the pp of an SS on the given map with the given mods.
+///
+/// Shorthand for [`BeatmapExt::max_pp`], e.g. to rank maps by their pp ceiling.
+#[inline]
+pub fn ss_pp(map: &Beatmap, mods: u32) -> f64 {
+    map.max_pp(mods).pp()
+}
+
 /// The result of calculating the strains on a map.
 /// Suitable to plot the difficulty of a map over time.
 #[derive(Clone, Debug, Default)]
@@ -495,3 +512,20 @@ fn difficulty_range(val: f64, max: f64, avg: f64, min: f64) -> f64 {
 
 #[cfg(all(feature = "async_tokio", feature = "async_std"))]
 compile_error!("Only one of the features `async_tokio` and `async_std` should be enabled");
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn ss_pp_matches_builder() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let mods = 8 + 16;
+
+        let expected = OsuPP::new(&map).mods(mods).calculate().pp;
+
+        assert_eq!(ss_pp(&map, mods), expected);
+    }
+}
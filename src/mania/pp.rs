@@ -249,3 +249,45 @@ impl ManiaAttributeProvider for PerformanceAttributes {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn mania_perfect_score() {
+        let map = Beatmap::from_path("./maps/1974394.osu").expect("failed to parse map");
+
+        let default = ManiaPP::new(&map).calculate();
+        let perfect = ManiaPP::new(&map).score(1_000_000).calculate();
+        let reused = ManiaPP::new(&map)
+            .attributes(perfect)
+            .score(1_000_000)
+            .calculate();
+
+        assert_eq!(default, perfect);
+        assert_eq!(perfect, reused);
+        assert!(perfect.pp > 0.0);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn mania_passed_objects() {
+        let map = Beatmap::from_path("./maps/1974394.osu").expect("failed to parse map");
+        let n = 100;
+
+        let full = ManiaPP::new(&map).score(1_000_000).calculate();
+        let partial = ManiaPP::new(&map)
+            .passed_objects(n)
+            .score(1_000_000 * n as u32 / map.hit_objects.len() as u32)
+            .calculate();
+
+        let stars = ManiaStars::new(&map).passed_objects(n).calculate().stars;
+
+        assert_eq!(partial.stars(), stars);
+        assert!(partial.stars() < full.stars());
+        assert!(partial.pp < full.pp);
+    }
+}
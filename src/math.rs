@@ -0,0 +1,101 @@
+//! Float functions used by the performance calculation.
+//!
+//! Without the `libm` feature these simply forward to `std`.
+//! With the feature, they use `libm` instead so that the calculation
+//! does not depend on `std`'s float intrinsics. The crate itself
+//! still requires `std` either way.
+
+#[cfg(not(feature = "libm"))]
+mod imp {
+    #[inline]
+    pub(crate) fn powf(x: f64, y: f64) -> f64 {
+        x.powf(y)
+    }
+
+    #[inline]
+    pub(crate) fn powi(x: f64, n: i32) -> f64 {
+        x.powi(n)
+    }
+
+    #[inline]
+    pub(crate) fn sqrt(x: f64) -> f64 {
+        x.sqrt()
+    }
+
+    #[inline]
+    pub(crate) fn log10(x: f64) -> f64 {
+        x.log10()
+    }
+
+    #[inline]
+    pub(crate) fn exp(x: f64) -> f64 {
+        x.exp()
+    }
+}
+
+#[cfg(feature = "libm")]
+mod imp {
+    #[inline]
+    pub(crate) fn powf(x: f64, y: f64) -> f64 {
+        libm::pow(x, y)
+    }
+
+    #[inline]
+    pub(crate) fn powi(x: f64, n: i32) -> f64 {
+        libm::pow(x, n as f64)
+    }
+
+    #[inline]
+    pub(crate) fn sqrt(x: f64) -> f64 {
+        libm::sqrt(x)
+    }
+
+    #[inline]
+    pub(crate) fn log10(x: f64) -> f64 {
+        libm::log10(x)
+    }
+
+    #[inline]
+    pub(crate) fn exp(x: f64) -> f64 {
+        libm::exp(x)
+    }
+}
+
+pub(crate) use imp::{exp, log10, powf, powi, sqrt};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        let diff = (actual - expected).abs();
+
+        assert!(
+            diff <= expected.abs() * 1e-14,
+            "{} differs from {} by {}",
+            actual,
+            expected,
+            diff
+        );
+    }
+
+    #[test]
+    fn matches_std() {
+        let values = [0.0, 0.0675, 0.5, 0.97, 1.0, 1.52163, 7.3, 113.0, 2345.6];
+
+        for &x in values.iter() {
+            assert_close(sqrt(x), x.sqrt());
+            assert_close(exp(-x), (-x).exp());
+            assert_close(powi(x, 3), x.powi(3));
+            assert_close(powi(x, 24), x.powi(24));
+
+            for &y in [0.8, 0.85, 1.1, 1.0 / 1.1, 1.17].iter() {
+                assert_close(powf(x, y), x.powf(y));
+            }
+
+            if x > 0.0 {
+                assert_close(log10(x), x.log10());
+            }
+        }
+    }
+}
@@ -1,3 +1,9 @@
+use std::{
+    error::Error as StdError,
+    fmt,
+    ops::{BitOr, BitOrAssign},
+};
+
 macro_rules! impl_mods {
     ($func_name:ident, $const_name:ident) => {
         #[inline]
@@ -8,6 +14,10 @@ macro_rules! impl_mods {
 }
 
 /// Abstract type to define mods.
+///
+/// Mods that only affect how a score is played back, i.e. Autoplay, Cinema,
+/// and Target Practice, are ignored by all calculations and the remaining mods
+/// are used instead. Use [`is_unranked`](Mods::is_unranked) to check for them.
 #[allow(missing_docs)]
 pub trait Mods: Copy {
     const NF: u32 = 1 << 0;
@@ -15,12 +25,21 @@ pub trait Mods: Copy {
     const TD: u32 = 1 << 2;
     const HD: u32 = 1 << 3;
     const HR: u32 = 1 << 4;
+    const SD: u32 = 1 << 5;
     const DT: u32 = 1 << 6;
     const RX: u32 = 1 << 7;
     const HT: u32 = 1 << 8;
+    /// Only ever set together with [`DT`](Mods::DT).
+    const NC: u32 = 1 << 9;
     const FL: u32 = 1 << 10;
+    const AT: u32 = 1 << 11;
     const SO: u32 = 1 << 12;
     const AP: u32 = 1 << 13;
+    const PF: u32 = 1 << 14;
+    const CN: u32 = 1 << 22;
+    const TP: u32 = 1 << 23;
+    /// Blinds is not part of osu!stable's mods so it uses an otherwise unused bit.
+    const BL: u32 = 1 << 31;
 
     /// If the clock rate is affected by the mods.
     fn change_speed(self) -> bool;
@@ -30,6 +49,47 @@ pub trait Mods: Copy {
     fn clock_rate(self) -> f64;
     /// Multiplier for beatmap attributes with respect to the mods.
     fn od_ar_hp_multiplier(self) -> f64;
+    /// If the mods contain Autoplay, Cinema, or Target Practice
+    /// for which scores don't award pp.
+    fn is_unranked(self) -> bool;
+    /// Remove mods that are mutually exclusive with a higher-priority mod.
+    ///
+    /// The precedence matches how the calculations already treat such combinations:
+    /// DT over HT, HR over EZ, and RX over AP.
+    fn sanitize_mods(self) -> u32;
+    /// Remove all mods that don't affect the difficulty attributes, e.g. to use
+    /// the result as key when caching attributes.
+    ///
+    /// Only EZ, HR, DT, HT, and FL are kept. EZ and HR change the map's attributes,
+    /// DT and HT its clock rate, and FL adds the flashlight rating. All other mods
+    /// such as NF, HD, SO, RX, or AP only affect the pp. NC is DT with an
+    /// additional bit so it results in the same value as DT.
+    ///
+    /// ```
+    /// use rosu_pp::Mods;
+    ///
+    /// // HDDT and DT share their difficulty attributes
+    /// assert_eq!(72.difficulty_affecting(), 64.difficulty_affecting());
+    /// ```
+    fn difficulty_affecting(self) -> u32;
+    /// Parse mods from their concatenated acronyms, e.g. `"HDDT"`, into their bit values.
+    ///
+    /// Acronyms are case-insensitive. An empty string or `"NM"` stand for no mods.
+    /// `"NC"` includes the bit of DT, just like osu! sets it.
+    ///
+    /// ```
+    /// use rosu_pp::Mods;
+    ///
+    /// assert_eq!(u32::from_acronyms("HDdt"), Ok(72));
+    /// assert_eq!(u32::from_acronyms("HDNC"), Ok(8 + 64 + 512));
+    /// assert!(u32::from_acronyms("HDXX").is_err());
+    /// ```
+    fn from_acronyms(acronyms: &str) -> Result<u32, ParseModsError>;
+    /// Concatenate the acronyms of all contained mods, e.g. `"HDDT"` for `72`.
+    ///
+    /// The acronyms are ordered by their bit value, just like osu! displays them,
+    /// and nomod results in an empty string. DT is omitted if NC is present.
+    fn to_acronyms(self) -> String;
     fn nf(self) -> bool;
     fn ez(self) -> bool;
     fn td(self) -> bool;
@@ -41,6 +101,7 @@ pub trait Mods: Copy {
     fn fl(self) -> bool;
     fn so(self) -> bool;
     fn ap(self) -> bool;
+    fn bl(self) -> bool;
 }
 
 impl Mods for u32 {
@@ -76,6 +137,64 @@ impl Mods for u32 {
         }
     }
 
+    #[inline]
+    fn is_unranked(self) -> bool {
+        self & (Self::AT | Self::CN | Self::TP) > 0
+    }
+
+    #[inline]
+    fn sanitize_mods(self) -> u32 {
+        let mut mods = self;
+
+        if mods.dt() {
+            mods &= !Self::HT;
+        }
+
+        if mods.hr() {
+            mods &= !Self::EZ;
+        }
+
+        if mods.rx() {
+            mods &= !Self::AP;
+        }
+
+        mods
+    }
+
+    #[inline]
+    fn difficulty_affecting(self) -> u32 {
+        self & (Self::EZ | Self::HR | Self::DT | Self::HT | Self::FL)
+    }
+
+    fn from_acronyms(acronyms: &str) -> Result<u32, ParseModsError> {
+        if acronyms.eq_ignore_ascii_case("NM") {
+            return Ok(0);
+        }
+
+        let chars: Vec<_> = acronyms.chars().collect();
+
+        chars.chunks(2).try_fold(0, |mods, chunk| {
+            let acronym: String = chunk.iter().collect();
+
+            ACRONYMS
+                .iter()
+                .find(|(_, known)| known.eq_ignore_ascii_case(&acronym))
+                .map(|(bit, _)| mods | bit)
+                .ok_or(ParseModsError(acronym))
+        })
+    }
+
+    fn to_acronyms(self) -> String {
+        // NC already implies DT
+        let hidden = if self & Self::NC > 0 { Self::DT } else { 0 };
+
+        ACRONYMS
+            .iter()
+            .filter(|(bit, _)| self & bit == *bit && *bit != hidden)
+            .map(|(_, acronym)| *acronym)
+            .collect()
+    }
+
     impl_mods!(nf, NF);
     impl_mods!(ez, EZ);
     impl_mods!(td, TD);
@@ -87,4 +206,272 @@ impl Mods for u32 {
     impl_mods!(fl, FL);
     impl_mods!(so, SO);
     impl_mods!(ap, AP);
+    impl_mods!(bl, BL);
+}
+
+/// All mods' bit values together with their acronym.
+const ACRONYMS: [(u32, &str); 18] = [
+    (u32::NF, "NF"),
+    (u32::EZ, "EZ"),
+    (u32::TD, "TD"),
+    (u32::HD, "HD"),
+    (u32::HR, "HR"),
+    (u32::SD, "SD"),
+    (u32::DT, "DT"),
+    (u32::RX, "RX"),
+    (u32::HT, "HT"),
+    (u32::DT | u32::NC, "NC"),
+    (u32::FL, "FL"),
+    (u32::AT, "AT"),
+    (u32::SO, "SO"),
+    (u32::AP, "AP"),
+    (u32::PF, "PF"),
+    (u32::CN, "CN"),
+    (u32::TP, "TP"),
+    (u32::BL, "BL"),
+];
+
+/// Error when parsing mods through [`Mods::from_acronyms`],
+/// containing the acronym that could not be recognized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseModsError(pub String);
+
+impl fmt::Display for ParseModsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown mod acronym `{}`", self.0)
+    }
+}
+
+impl StdError for ParseModsError {}
+
+/// Set of mods that can be combined through `|` instead of adding up raw bit values.
+///
+/// Calculators that accept [`IntoMods`] take either this type or the raw `u32` bits.
+///
+/// # Example
+///
+/// ```
+/// use rosu_pp::GameMods;
+///
+/// let mods = GameMods::HD | GameMods::DT;
+/// assert_eq!(u32::from(mods), 72);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GameMods(u32);
+
+#[allow(missing_docs)]
+impl GameMods {
+    pub const NM: Self = Self(0);
+    pub const NF: Self = Self(<u32 as Mods>::NF);
+    pub const EZ: Self = Self(<u32 as Mods>::EZ);
+    pub const TD: Self = Self(<u32 as Mods>::TD);
+    pub const HD: Self = Self(<u32 as Mods>::HD);
+    pub const HR: Self = Self(<u32 as Mods>::HR);
+    pub const SD: Self = Self(<u32 as Mods>::SD);
+    pub const DT: Self = Self(<u32 as Mods>::DT);
+    pub const RX: Self = Self(<u32 as Mods>::RX);
+    pub const HT: Self = Self(<u32 as Mods>::HT);
+    /// Includes [`DT`](GameMods::DT) since both are always set together.
+    pub const NC: Self = Self(<u32 as Mods>::DT | <u32 as Mods>::NC);
+    pub const FL: Self = Self(<u32 as Mods>::FL);
+    pub const AT: Self = Self(<u32 as Mods>::AT);
+    pub const SO: Self = Self(<u32 as Mods>::SO);
+    pub const AP: Self = Self(<u32 as Mods>::AP);
+    pub const PF: Self = Self(<u32 as Mods>::PF);
+    pub const CN: Self = Self(<u32 as Mods>::CN);
+    pub const TP: Self = Self(<u32 as Mods>::TP);
+    pub const BL: Self = Self(<u32 as Mods>::BL);
+}
+
+impl GameMods {
+    /// Create mods from their raw bit values.
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Return the raw bit values.
+    #[inline]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Check whether all of `other`'s mods are contained.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for GameMods {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for GameMods {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl From<GameMods> for u32 {
+    #[inline]
+    fn from(mods: GameMods) -> Self {
+        mods.0
+    }
+}
+
+impl From<u32> for GameMods {
+    #[inline]
+    fn from(bits: u32) -> Self {
+        Self(bits)
+    }
+}
+
+/// Abstract type for anything that can be used as mods, i.e. `u32` bits or [`GameMods`].
+pub trait IntoMods {
+    /// Convert into the raw bit values.
+    fn into_bits(self) -> u32;
+}
+
+impl IntoMods for u32 {
+    #[inline]
+    fn into_bits(self) -> u32 {
+        self
+    }
+}
+
+impl IntoMods for GameMods {
+    #[inline]
+    fn into_bits(self) -> u32 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Beatmap, OsuPP};
+
+    #[test]
+    fn combine_flags() {
+        let mods = GameMods::HD | GameMods::DT;
+
+        assert_eq!(u32::from(mods), 72);
+        assert!(mods.contains(GameMods::HD));
+        assert!(!mods.contains(GameMods::HD | GameMods::HR));
+
+        let mut mods = GameMods::NM;
+        mods |= GameMods::FL;
+        assert_eq!(mods, GameMods::from_bits(1024));
+
+        let mods = GameMods::HD | GameMods::NC;
+        assert!(mods.contains(GameMods::DT));
+        assert_eq!(mods.bits(), u32::from_acronyms("HDNC").unwrap());
+        assert_eq!(mods.bits().to_acronyms(), "HDNC");
+
+        let mods = GameMods::SD | GameMods::PF;
+        assert_eq!(mods.bits(), u32::from_acronyms("SDPF").unwrap());
+    }
+
+    #[test]
+    fn sanitize_exclusive_mods() {
+        let rx = <u32 as Mods>::RX;
+        let ap = <u32 as Mods>::AP;
+        let dt = <u32 as Mods>::DT;
+        let ht = <u32 as Mods>::HT;
+        let hd = <u32 as Mods>::HD;
+
+        assert_eq!((rx | ap).sanitize_mods(), rx);
+        assert_eq!((dt | ht | hd).sanitize_mods(), dt | hd);
+        assert_eq!((<u32 as Mods>::HR | <u32 as Mods>::EZ).sanitize_mods(), 16);
+        assert_eq!((hd | ap).sanitize_mods(), hd | ap);
+    }
+
+    #[test]
+    fn parse_acronyms() {
+        assert_eq!(u32::from_acronyms("HDDTHR"), Ok(8 + 64 + 16));
+        assert_eq!(u32::from_acronyms("hdDtHr"), Ok(8 + 64 + 16));
+        assert_eq!(u32::from_acronyms("RXSONFTD"), Ok(128 + 4096 + 1 + 4));
+        assert_eq!(u32::from_acronyms("APHTFL"), Ok(8192 + 256 + 1024));
+        assert_eq!(u32::from_acronyms(""), Ok(0));
+        assert_eq!(u32::from_acronyms("nm"), Ok(0));
+        assert_eq!(u32::from_acronyms("HDNC"), Ok(8 + 64 + 512));
+        assert_eq!(u32::from_acronyms("HDDTPF"), Ok(8 + 64 + 16384));
+        assert_eq!(u32::from_acronyms("SDHR"), Ok(32 + 16));
+        assert_eq!(u32::from_acronyms("DTNC"), u32::from_acronyms("NC"));
+
+        assert_eq!(
+            u32::from_acronyms("HDXYDT"),
+            Err(ParseModsError("XY".to_owned()))
+        );
+        assert_eq!(
+            u32::from_acronyms("HDD"),
+            Err(ParseModsError("D".to_owned()))
+        );
+    }
+
+    #[test]
+    fn format_acronyms() {
+        assert_eq!(0.to_acronyms(), "");
+        assert_eq!(8.to_acronyms(), "HD");
+        assert_eq!(128.to_acronyms(), "RX");
+        assert_eq!(8192.to_acronyms(), "AP");
+        assert_eq!(72.to_acronyms(), "HDDT");
+        assert_eq!((1024 + 64 + 16 + 8).to_acronyms(), "HDHRDTFL");
+        assert_eq!((8192 + 4096 + 1).to_acronyms(), "NFSOAP");
+        assert_eq!((8 + 64 + 512).to_acronyms(), "HDNC");
+        assert_eq!((16 + 32).to_acronyms(), "HRSD");
+        assert_eq!((8 + 64 + 16384).to_acronyms(), "HDDTPF");
+
+        for acronyms in ["HDNC", "HDDTPF", "SDDT"].iter() {
+            let mods = u32::from_acronyms(acronyms).unwrap();
+            assert_eq!(u32::from_acronyms(&mods.to_acronyms()), Ok(mods));
+        }
+
+        let parsed = u32::from_acronyms("FLDThd").unwrap();
+        assert_eq!(parsed.to_acronyms(), "HDDTFL");
+        assert_eq!(u32::from_acronyms(&parsed.to_acronyms()), Ok(parsed));
+    }
+
+    #[test]
+    fn difficulty_affecting_mods() {
+        assert_eq!(1.difficulty_affecting(), 0.difficulty_affecting());
+        assert_eq!((4096 + 128 + 8 + 4).difficulty_affecting(), 0);
+        assert_eq!((512 + 64).difficulty_affecting(), 64);
+        assert_eq!(
+            (1024 + 256 + 16 + 2).difficulty_affecting(),
+            1024 + 256 + 16 + 2
+        );
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn difficulty_affecting_stars() {
+        use crate::OsuStars;
+
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let stars = |mods: u32| OsuStars::new(&map).mods(mods).calculate();
+
+        assert_eq!(stars(1), stars(0));
+        assert_eq!(stars(4096 + 8), stars(0));
+        assert_ne!(stars(16), stars(0));
+        assert_ne!(stars(1024), stars(0));
+    }
+
+    #[test]
+    fn builder_accepts_both() {
+        let map = Beatmap::default();
+
+        let flags = OsuPP::new(&map).mods(GameMods::HD | GameMods::DT);
+        let bits = OsuPP::new(&map).mods(72);
+
+        assert_eq!(flags.mods, 72);
+        assert_eq!(bits.mods, 72);
+    }
 }
@@ -0,0 +1,36 @@
+/// How the accuracy of a score is weighted.
+///
+/// - With [`AccuracyMode::Standard`], each object is worth 6/2/1 points for a
+///   300/100/50 out of a maximum of 6 points.
+/// - With [`AccuracyMode::ScoreV2`], slider heads are judged on their own and
+///   count as an additional 300 each, so the maximum is 6 points per object plus
+///   6 points per slider. Heads are assumed to be hit unless the slider was
+///   missed, i.e. each miss is assumed to drop one slider head.
+///
+/// Defaults to [`AccuracyMode::Standard`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AccuracyMode {
+    /// osu!standard accuracy based on the object hitresults only.
+    Standard,
+    /// ScoreV2 accuracy that additionally judges slider heads.
+    ScoreV2,
+}
+
+impl AccuracyMode {
+    /// The amount of slider heads that contribute to the accuracy and how many
+    /// of them are assumed to be hit.
+    #[inline]
+    pub(crate) fn slider_heads(self, n_sliders: usize, n_misses: usize) -> (usize, usize) {
+        match self {
+            Self::Standard => (0, 0),
+            Self::ScoreV2 => (n_sliders, n_sliders.saturating_sub(n_misses)),
+        }
+    }
+}
+
+impl Default for AccuracyMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Standard
+    }
+}
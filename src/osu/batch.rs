@@ -0,0 +1,149 @@
+use crate::{Beatmap, OsuPP, OsuStars};
+
+use super::{OsuDifficultyAttributes, OsuPerformanceAttributes, OsuScoreState};
+
+/// Calculate the performance of many scores on the same map-mod combination.
+///
+/// The difficulty attributes are calculated only once on creation
+/// and then reused for every score.
+///
+/// Created through [`OsuPP::new_batch`](crate::OsuPP::new_batch).
+///
+/// # Example
+///
+/// ```
+/// use rosu_pp::{Beatmap, OsuPP, osu::OsuScoreState};
+///
+/// # /*
+/// let map: Beatmap = ...
+/// # */
+/// # let map = Beatmap::default();
+/// # /*
+/// let batch = OsuPP::new_batch(&map, 8 + 64); // HDDT
+///
+/// let scores: Vec<OsuScoreState> = ...
+///
+/// for state in scores {
+///     println!("PP: {}", batch.calculate_state(state).pp);
+/// }
+/// # */
+/// ```
+#[derive(Clone, Debug)]
+pub struct OsuBatchCalculator<'map> {
+    map: &'map Beatmap,
+    mods: u32,
+    attributes: OsuDifficultyAttributes,
+}
+
+impl<'map> OsuBatchCalculator<'map> {
+    pub(crate) fn new(map: &'map Beatmap, mods: u32) -> Self {
+        let attributes = OsuStars::new(map).mods(mods).calculate();
+
+        Self {
+            map,
+            mods,
+            attributes,
+        }
+    }
+
+    /// Return the difficulty attributes that are used for every score.
+    #[inline]
+    pub fn attributes(&self) -> &OsuDifficultyAttributes {
+        &self.attributes
+    }
+
+    /// Calculate the performance attributes of the given score state.
+    pub fn calculate_state(&self, state: OsuScoreState) -> OsuPerformanceAttributes {
+        OsuPP::new(self.map)
+            .mods(self.mods)
+            .attributes(self.attributes.clone())
+            .state(state)
+            .calculate()
+    }
+
+    /// Calculate only the pp of each given score state.
+    ///
+    /// Same values as the pp of [`calculate_state`](OsuBatchCalculator::calculate_state)
+    /// but no performance attributes are created. The pp are in the same order as the states.
+    pub fn calculate_pp(&self, states: &[OsuScoreState]) -> Vec<f64> {
+        let mut calculator = OsuPP::new(self.map).mods(self.mods);
+
+        states
+            .iter()
+            .map(|state| {
+                calculator.set_state(state);
+
+                calculator.calculate_pp_with(&self.attributes)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn single_difficulty_calculation() {
+        use super::super::STARS_CALCULATIONS;
+
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let mods = 8 + 64;
+
+        STARS_CALCULATIONS.with(|count| count.set(0));
+        let batch = OsuPP::new_batch(&map, mods);
+
+        for i in 0..10_000 {
+            let state = OsuScoreState {
+                max_combo: 909 - i % 500,
+                n300: 601 - i % 50 - i % 3,
+                n100: i % 50,
+                n50: 0,
+                misses: i % 3,
+            };
+
+            let _ = batch.calculate_state(state);
+        }
+
+        assert_eq!(STARS_CALCULATIONS.with(|count| count.get()), 1);
+
+        let state = OsuScoreState {
+            max_combo: 800,
+            n300: 580,
+            n100: 19,
+            n50: 1,
+            misses: 1,
+        };
+
+        let expected = OsuPP::new(&map).mods(mods).state(state.clone()).calculate();
+
+        assert_eq!(batch.calculate_state(state), expected);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn pp_only() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let batch = OsuPP::new_batch(&map, 8 + 16);
+
+        let states: Vec<_> = (0..100)
+            .map(|i| OsuScoreState {
+                max_combo: 909 - i * 5,
+                n300: 601 - i - i % 4,
+                n100: i,
+                n50: 0,
+                misses: i % 4,
+            })
+            .collect();
+
+        let pp = batch.calculate_pp(&states);
+
+        assert_eq!(pp.len(), states.len());
+
+        for (pp, state) in pp.into_iter().zip(states) {
+            assert_eq!(pp, batch.calculate_state(state).pp);
+        }
+    }
+}
@@ -103,6 +103,7 @@ impl OsuGradualDifficultyAttributes {
         attributes.n_sliders = 0;
         attributes.n_spinners = 0;
         attributes.max_combo = 0;
+        attributes.avg_slider_velocity = 0.0;
 
         let stack_threshold = time_preempt * map.stack_leniency as f64;
 
@@ -151,9 +152,16 @@ impl Iterator for OsuGradualDifficultyAttributes {
 
         match &curr.kind {
             OsuObjectKind::Circle => self.attributes.n_circles += 1,
-            OsuObjectKind::Slider { nested_objects, .. } => {
+            OsuObjectKind::Slider {
+                nested_objects,
+                velocity,
+                ..
+            } => {
                 self.attributes.max_combo += nested_objects.len();
-                self.attributes.n_sliders += 1
+                self.attributes.n_sliders += 1;
+
+                let avg_velocity = &mut self.attributes.avg_slider_velocity;
+                *avg_velocity += (velocity - *avg_velocity) / self.attributes.n_sliders as f64;
             }
             OsuObjectKind::Spinner { .. } => self.attributes.n_spinners += 1,
         };
@@ -165,6 +173,10 @@ impl Iterator for OsuGradualDifficultyAttributes {
             self.curr_section_end =
                 (self.prev.time / self.clock_rate / SECTION_LEN).ceil() * SECTION_LEN;
 
+            // A single object has no strain but still gets the base rating
+            self.attributes.slider_factor = 1.0;
+            self.attributes.stars = calculate_star_rating(0.0, 0.0, 0.0);
+
             return Some(self.attributes.clone());
         }
 
@@ -256,10 +268,18 @@ impl Iterator for OsuGradualDifficultyAttributes {
 
         let star_rating = calculate_star_rating(aim_rating, speed_rating, flashlight_rating);
 
+        let aim_difficult_strain_count = self.skills.aim().count_difficult_strains();
+        let speed_difficult_strain_count = match self.skills.speed_flashlight().0 {
+            Some(speed) => speed.count_difficult_strains(),
+            None => 0.0,
+        };
+
         self.attributes.aim_strain = aim_rating;
         self.attributes.speed_strain = speed_rating;
         self.attributes.flashlight_rating = flashlight_rating;
         self.attributes.slider_factor = slider_factor;
+        self.attributes.aim_difficult_strain_count = aim_difficult_strain_count;
+        self.attributes.speed_difficult_strain_count = speed_difficult_strain_count;
         self.attributes.stars = star_rating;
 
         Some(self.attributes.clone())
@@ -333,4 +353,24 @@ mod tests {
 
         assert_eq!(regular, iter_end);
     }
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn iter_eq_regular_passed() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let mods = 64;
+
+        // The 259th object is the map's only spinner
+        for &n in &[1, 2, 100, 258, 259, 260, 500] {
+            let regular = crate::OsuStars::new(&map)
+                .mods(mods)
+                .passed_objects(n)
+                .calculate();
+
+            let gradual = OsuGradualDifficultyAttributes::new(&map, mods)
+                .nth(n - 1)
+                .expect("missing attributes");
+
+            assert_eq!(regular, gradual, "passed objects: {}", n);
+        }
+    }
 }
@@ -1,12 +1,15 @@
+use std::convert::TryFrom;
+
 use crate::{Beatmap, OsuPP};
 
-use super::{OsuGradualDifficultyAttributes, OsuPerformanceAttributes};
+use super::{OsuGradualDifficultyAttributes, OsuPerformanceAttributes, ScoreStateError};
 
 /// Aggregation for a score's current state i.e. what was the
 /// maximum combo so far and what are the current hitresults.
 ///
 /// This struct is used for [`OsuGradualPerformanceAttributes`].
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OsuScoreState {
     /// Maximum combo that the score has had so far.
     /// **Not** the maximum possible combo of the map so far.
@@ -26,6 +29,89 @@ impl OsuScoreState {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Create a score state from raw, potentially inconsistent, counts
+    /// e.g. of a replay.
+    ///
+    /// If the hitresults sum up to more than `n_objects`, the excess is removed
+    /// from the 300s first, then from the 100s, 50s, and misses.
+    /// The combo is clamped to `map_max_combo`.
+    pub fn sanitized(
+        n_objects: usize,
+        map_max_combo: usize,
+        max_combo: usize,
+        n300: usize,
+        n100: usize,
+        n50: usize,
+        misses: usize,
+    ) -> Self {
+        let mut counts = [n300, n100, n50, misses];
+        let mut excess = counts.iter().sum::<usize>().saturating_sub(n_objects);
+
+        for count in counts.iter_mut() {
+            let removed = excess.min(*count);
+            *count -= removed;
+            excess -= removed;
+        }
+
+        let [n300, n100, n50, misses] = counts;
+
+        Self {
+            max_combo: max_combo.min(map_max_combo),
+            n300,
+            n100,
+            n50,
+            misses,
+        }
+    }
+}
+
+impl From<&OsuPerformanceAttributes> for OsuScoreState {
+    #[inline]
+    fn from(attributes: &OsuPerformanceAttributes) -> Self {
+        attributes.state.clone()
+    }
+}
+
+/// Validates that the state's hitresults and combo fit the difficulty attributes.
+impl TryFrom<OsuPerformanceAttributes> for OsuScoreState {
+    type Error = ScoreStateError;
+
+    fn try_from(attributes: OsuPerformanceAttributes) -> Result<Self, Self::Error> {
+        let OsuPerformanceAttributes {
+            difficulty, state, ..
+        } = attributes;
+
+        let n_objects = difficulty.n_circles + difficulty.n_sliders + difficulty.n_spinners;
+        let total = state.n300 + state.n100 + state.n50 + state.misses;
+
+        if total > n_objects {
+            return Err(ScoreStateError::TooManyHitResults { total, n_objects });
+        }
+
+        if state.max_combo > difficulty.max_combo {
+            return Err(ScoreStateError::ComboTooHigh {
+                combo: state.max_combo,
+                max_combo: difficulty.max_combo,
+            });
+        }
+
+        Ok(state)
+    }
+}
+
+/// Converts into `(max_combo, n300, n100, n50, misses)`.
+impl From<OsuScoreState> for (usize, usize, usize, usize, usize) {
+    #[inline]
+    fn from(state: OsuScoreState) -> Self {
+        (
+            state.max_combo,
+            state.n300,
+            state.n100,
+            state.n50,
+            state.misses,
+        )
+    }
 }
 
 /// Gradually calculate the performance attributes of an osu!standard map.
@@ -118,6 +204,8 @@ impl OsuScoreState {
 pub struct OsuGradualPerformanceAttributes<'map> {
     difficulty: OsuGradualDifficultyAttributes,
     performance: OsuPP<'map>,
+    attribution: Option<Vec<f64>>,
+    last_pp: f64,
 }
 
 impl<'map> OsuGradualPerformanceAttributes<'map> {
@@ -129,9 +217,39 @@ impl<'map> OsuGradualPerformanceAttributes<'map> {
         Self {
             difficulty,
             performance,
+            attribution: None,
+            last_pp: 0.0,
         }
     }
 
+    /// Record how much pp each processed object contributed,
+    /// e.g. to find the sections of a map that are worth the most pp.
+    ///
+    /// The contribution of an object is the pp difference to the previous calculation.
+    /// When processing multiple objects at once, the difference is split evenly
+    /// between them so the attribution is only as fine-grained as the processed steps.
+    /// Recording itself is cheap but per-object values require processing the objects
+    /// one by one, i.e. a full performance calculation for each object.
+    ///
+    /// The recorded values are accessible through
+    /// [`attribution`](OsuGradualPerformanceAttributes::attribution).
+    #[inline]
+    pub fn with_attribution(mut self) -> Self {
+        self.attribution = Some(Vec::with_capacity(self.difficulty.len()));
+
+        self
+    }
+
+    /// Return the pp contribution of each processed object if
+    /// [`with_attribution`](OsuGradualPerformanceAttributes::with_attribution) was used.
+    ///
+    /// The values are aligned to the map's hit objects and sum up to the pp
+    /// of the latest calculation.
+    #[inline]
+    pub fn attribution(&self) -> Option<&[f64]> {
+        self.attribution.as_deref()
+    }
+
     /// Process the next hit object and calculate the
     /// performance attributes for the resulting score state.
     pub fn process_next_object(
@@ -152,6 +270,7 @@ impl<'map> OsuGradualPerformanceAttributes<'map> {
         state: OsuScoreState,
         n: usize,
     ) -> Option<OsuPerformanceAttributes> {
+        let prev_idx = self.difficulty.idx;
         let n = n.min(self.difficulty.len()).saturating_sub(1);
         let difficulty = self.difficulty.nth(n)?;
 
@@ -163,6 +282,13 @@ impl<'map> OsuGradualPerformanceAttributes<'map> {
             .passed_objects(self.difficulty.idx)
             .calculate();
 
+        if let Some(ref mut attribution) = self.attribution {
+            let processed = self.difficulty.idx - prev_idx;
+            let delta = (performance.pp - self.last_pp) / processed as f64;
+            attribution.resize(attribution.len() + processed, delta);
+            self.last_pp = performance.pp;
+        }
+
         Some(performance)
     }
 }
@@ -172,6 +298,98 @@ mod tests {
     #[allow(unused_imports)]
     use super::*;
 
+    #[test]
+    fn sanitized_state() {
+        let state = OsuScoreState::sanitized(100, 150, 200, 90, 15, 5, 3);
+
+        assert_eq!(state.n300 + state.n100 + state.n50 + state.misses, 100);
+        assert_eq!(state.n300, 77);
+        assert_eq!(state.n100, 15);
+        assert_eq!(state.max_combo, 150);
+
+        let state = OsuScoreState::sanitized(10, 150, 20, 0, 4, 5, 6);
+
+        assert_eq!(state.n100 + state.n50 + state.misses, 10);
+        assert_eq!(state.misses, 6);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn attribution() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let mods = 128;
+
+        let mut gradual = OsuGradualPerformanceAttributes::new(&map, mods).with_attribution();
+        let n_objects = map.hit_objects.len();
+        let mut processed = 0;
+        let mut last = None;
+
+        // One by one at first, then in steps of 10
+        while processed < n_objects {
+            let n = if processed < 300 { 1 } else { 10 };
+            processed = (processed + n).min(n_objects);
+
+            let state = OsuScoreState {
+                max_combo: processed,
+                n300: processed,
+                ..Default::default()
+            };
+
+            last = gradual.process_next_n_objects(state, n).or(last);
+        }
+
+        let attribution = gradual.attribution().unwrap();
+        assert_eq!(attribution.len(), n_objects);
+
+        let sum: f64 = attribution.iter().sum();
+        let final_pp = last.unwrap().pp;
+        assert!((sum - final_pp).abs() < 1e-6, "{} vs {}", sum, final_pp);
+
+        let gradual = OsuGradualPerformanceAttributes::new(&map, mods);
+        assert!(gradual.attribution().is_none());
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn state_roundtrip() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let performance = OsuPP::new(&map)
+            .mods(8)
+            .combo(700)
+            .misses(2)
+            .accuracy(97.0)
+            .calculate();
+
+        let state = OsuScoreState::from(&performance);
+        assert_eq!(state.max_combo, 700);
+        assert_eq!(state.misses, 2);
+        assert_eq!(state.n300 + state.n100 + state.n50 + state.misses, 601);
+
+        let recalculated = OsuPP::new(&map).mods(8).state(state.clone()).calculate();
+        assert_eq!(recalculated, performance);
+
+        let (max_combo, n300, n100, n50, misses) = state.clone().into();
+        assert_eq!(
+            (max_combo, n300, n100, n50, misses),
+            (700, state.n300, state.n100, state.n50, 2)
+        );
+
+        assert_eq!(OsuScoreState::try_from(performance.clone()), Ok(state));
+
+        let mut invalid = performance;
+        invalid.state.n300 += 1;
+        invalid.state.max_combo = invalid.difficulty.max_combo + 1;
+
+        assert_eq!(
+            OsuScoreState::try_from(invalid),
+            Err(ScoreStateError::TooManyHitResults {
+                total: 602,
+                n_objects: 601
+            })
+        );
+    }
+
     #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
     #[test]
     fn correct_empty() {
@@ -187,6 +405,25 @@ mod tests {
         assert!(gradual.process_next_object(state).is_none());
     }
 
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn incremental_difficulty() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let mods = 64;
+        let state = OsuScoreState::default();
+
+        let mut gradual = OsuGradualPerformanceAttributes::new(&map, mods);
+        let mut difficulty = OsuGradualDifficultyAttributes::new(&map, mods);
+
+        for i in 1..=20 {
+            let performance = gradual.process_next_object(state.clone()).unwrap();
+
+            // Every object advances the shared difficulty state by exactly one step
+            assert_eq!(gradual.difficulty.idx, i);
+            assert_eq!(performance.difficulty, difficulty.next().unwrap());
+        }
+    }
+
     #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
     #[test]
     fn next_and_next_n() {
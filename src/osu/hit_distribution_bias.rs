@@ -0,0 +1,19 @@
+/// How [`OsuPP::accuracy`](crate::OsuPP::accuracy) distributes hitresults
+/// if none were specified beforehand.
+///
+/// Defaults to [`HitDistributionBias::FewerFifties`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HitDistributionBias {
+    /// Turn as many 50s into 100s as possible, sacrificing 300s for it.
+    /// Typically results in no 50s at all.
+    FewerFifties,
+    /// Aim for ten 100s per 50, which is closer to how players actually score.
+    Realistic,
+}
+
+impl Default for HitDistributionBias {
+    #[inline]
+    fn default() -> Self {
+        Self::FewerFifties
+    }
+}
@@ -1,26 +1,52 @@
+mod accuracy_mode;
+mod batch;
 mod difficulty_object;
 mod gradual_difficulty;
 mod gradual_performance;
+mod hit_distribution_bias;
 mod osu_object;
 mod pp;
+mod pp_trace;
+mod relax_acc_profile;
 mod scaling_factor;
+mod score_report;
+mod score_state_error;
+mod scoring_mode;
 mod skill;
 mod skill_kind;
 mod slider_state;
 
 use std::mem;
 
+#[cfg(not(feature = "async_std"))]
+use std::path::Path;
+
+#[cfg(feature = "async_std")]
+use async_std::path::Path;
+
+pub use accuracy_mode::AccuracyMode;
+pub use batch::OsuBatchCalculator;
 use difficulty_object::DifficultyObject;
 pub use gradual_difficulty::*;
 pub use gradual_performance::*;
-use osu_object::{ObjectParameters, OsuObject};
+pub use hit_distribution_bias::HitDistributionBias;
+use osu_object::{ObjectParameters, OsuObject, SliderTicks};
 pub use pp::*;
+pub use pp_trace::*;
+pub use relax_acc_profile::RelaxAccProfile;
 use scaling_factor::ScalingFactor;
+pub use score_report::ScoreReport;
+pub use score_state_error::ScoreStateError;
+pub use scoring_mode::ScoringMode;
 use skill::Skill;
 use skill_kind::SkillKind;
 use slider_state::SliderState;
 
-use crate::{curve::CurveBuffers, Beatmap, Mods, Strains};
+use crate::{
+    curve::{Curve, CurveBuffers},
+    parse::HitObjectKind,
+    Beatmap, Mods, ParseResult, Strains,
+};
 
 use self::skill::Skills;
 
@@ -29,6 +55,12 @@ const DIFFICULTY_MULTIPLIER: f64 = 0.0675;
 const NORMALIZED_RADIUS: f32 = 50.0; // * diameter of 100; easier mental maths.
 const STACK_DISTANCE: f32 = 3.0;
 
+#[cfg(test)]
+thread_local! {
+    /// How often [`OsuStars::calculate`] was called on the current thread.
+    static STARS_CALCULATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 /// Difficulty calculator on osu!standard maps.
 ///
 /// # Example
@@ -102,6 +134,9 @@ impl<'map> OsuStars<'map> {
     /// Calculate all difficulty related values, including stars.
     #[inline]
     pub fn calculate(self) -> OsuDifficultyAttributes {
+        #[cfg(test)]
+        STARS_CALCULATIONS.with(|count| count.set(count.get() + 1));
+
         let (mut skills, mut attributes) = calculate_skills(self);
 
         let aim_rating = {
@@ -207,6 +242,81 @@ impl<'map> OsuStars<'map> {
     }
 }
 
+/// Parse the `.osu` file at the given path and calculate the performance of the score state.
+///
+/// Shorthand for [`Beatmap::from_path`] followed by an [`OsuPP`] calculation,
+/// e.g. for quick command line tools. Parsing errors are returned as is.
+#[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+pub fn calculate_from_path<P: AsRef<Path>>(
+    path: P,
+    mods: u32,
+    state: OsuScoreState,
+) -> ParseResult<OsuPerformanceAttributes> {
+    let map = Beatmap::from_path(path)?;
+
+    Ok(OsuPP::new(&map).mods(mods).state(state).calculate())
+}
+
+/// Parse the `.osu` file at the given path and calculate the performance of the score state.
+///
+/// Shorthand for [`Beatmap::from_path`] followed by an [`OsuPP`] calculation,
+/// e.g. for quick command line tools. Parsing errors are returned as is.
+#[cfg(any(feature = "async_tokio", feature = "async_std"))]
+pub async fn calculate_from_path<P: AsRef<Path>>(
+    path: P,
+    mods: u32,
+    state: OsuScoreState,
+) -> ParseResult<OsuPerformanceAttributes> {
+    let map = Beatmap::from_path(path).await?;
+
+    Ok(OsuPP::new(&map).mods(mods).state(state).calculate())
+}
+
+/// Calculate the performance of many scores in parallel, e.g. to recompute a leaderboard.
+///
+/// Each entry consists of a map, the mods, and the score state. Difficulty attributes
+/// are calculated separately for every entry. The results are in the same order as the input.
+#[cfg(feature = "parallel")]
+pub fn calculate_many(scores: &[(&Beatmap, u32, OsuScoreState)]) -> Vec<OsuPerformanceAttributes> {
+    use rayon::prelude::*;
+
+    scores
+        .par_iter()
+        .map(|(map, mods, state)| OsuPP::new(map).mods(*mods).state(state.clone()).calculate())
+        .collect()
+}
+
+impl Beatmap {
+    /// Calculate the osu!standard max combo of the map without
+    /// going through the whole difficulty calculation.
+    ///
+    /// Circles, spinners, and slider heads count for one combo each,
+    /// slider ticks, repeats, and tails are added on top.
+    pub fn max_combo(&self) -> usize {
+        let mut slider_state = SliderState::new(self);
+        let mut curve_bufs = CurveBuffers::default();
+
+        self.hit_objects
+            .iter()
+            .map(|h| match &h.kind {
+                HitObjectKind::Circle | HitObjectKind::Spinner { .. } => 1,
+                HitObjectKind::Slider {
+                    pixel_len,
+                    repeats,
+                    control_points,
+                } => {
+                    slider_state.update(h.start_time);
+
+                    let curve = Curve::new(control_points, *pixel_len, &mut curve_bufs);
+
+                    1 + SliderTicks::new(self, &slider_state, &curve).n_nested_objects(*repeats)
+                }
+                HitObjectKind::Hold { .. } => 0,
+            })
+            .sum()
+    }
+}
+
 fn calculate_star_rating(aim_rating: f64, speed_rating: f64, flashlight_rating: f64) -> f64 {
     let base_aim_performance = {
         let base = 5.0 * (aim_rating / 0.0675).max(1.0) - 4.0;
@@ -515,6 +625,7 @@ fn lerp(start: f64, end: f64, percent: f64) -> f64 {
 
 /// The result of a difficulty calculation on an osu!standard map.
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OsuDifficultyAttributes {
     /// The aim portion of the total strain.
     pub aim_strain: f64,
@@ -542,6 +653,9 @@ pub struct OsuDifficultyAttributes {
     pub stars: f64,
     /// The maximum combo.
     pub max_combo: usize,
+    /// The average velocity of sliders in osu!pixels per millisecond
+    /// without considering the clock rate.
+    pub avg_slider_velocity: f64,
 
     /// Aim difficult strain count
     aim_difficult_strain_count: f64,
@@ -555,10 +669,24 @@ impl OsuDifficultyAttributes {
     pub fn max_combo(&self) -> usize {
         self.max_combo
     }
+
+    /// Clamp invalid difficult strain counts, e.g. from external sources,
+    /// so that they don't produce garbage miss penalties.
+    pub(crate) fn sanitize(&mut self) {
+        fn sanitize_count(count: &mut f64) {
+            if count.is_nan() || *count < 0.0 {
+                *count = 0.0;
+            }
+        }
+
+        sanitize_count(&mut self.aim_difficult_strain_count);
+        sanitize_count(&mut self.speed_difficult_strain_count);
+    }
 }
 
 /// The result of a performance calculation on an osu!standard map.
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OsuPerformanceAttributes {
     /// The difficulty attributes that were used for the performance calculation
     pub difficulty: OsuDifficultyAttributes,
@@ -567,11 +695,34 @@ pub struct OsuPerformanceAttributes {
     /// The accuracy portion of the final pp.
     pub pp_acc: f64,
     /// The aim portion of the final pp.
+    ///
+    /// Always `0.0` for autopilot scores since aim does not contribute for them.
     pub pp_aim: f64,
     /// The flashlight portion of the final pp.
     pub pp_flashlight: f64,
     /// The speed portion of the final pp.
+    ///
+    /// For relax scores this only contributes with respect to
+    /// [`OsuPP::rx_speed_weight`](crate::OsuPP::rx_speed_weight).
     pub pp_speed: f64,
+    /// The map quality factor that was applied on the final pp.
+    pub quality_multiplier: f64,
+    /// The bonus for longer maps that was applied on aim and speed.
+    pub len_bonus: f64,
+    /// The miss penalty that was applied on aim.
+    pub aim_miss_penalty: f64,
+    /// The miss penalty that was applied on speed.
+    pub speed_miss_penalty: f64,
+    /// The amount of misses that the penalties were based on.
+    ///
+    /// Includes slider breaks which are estimated from the combo unless
+    /// they were specified through [`OsuPP::slider_breaks`](crate::OsuPP::slider_breaks).
+    pub effective_misses: f64,
+    /// The score state that the calculation resolved to.
+    pub state: OsuScoreState,
+    /// The mods that the calculation was based on.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mods: u32,
 }
 
 impl OsuPerformanceAttributes {
@@ -592,6 +743,253 @@ impl OsuPerformanceAttributes {
     pub fn max_combo(&self) -> usize {
         self.difficulty.max_combo
     }
+
+    /// Return the aim strain of the difficulty attributes.
+    ///
+    /// ```
+    /// use rosu_pp::{Beatmap, OsuPP};
+    ///
+    /// # /*
+    /// let map: Beatmap = ...
+    /// # */
+    /// # let map = Beatmap::default();
+    /// let performance = OsuPP::new(&map).calculate();
+    ///
+    /// assert_eq!(performance.aim_strain(), performance.difficulty.aim_strain);
+    /// assert_eq!(performance.speed_strain(), performance.difficulty.speed_strain);
+    /// assert_eq!(
+    ///     performance.flashlight_rating(),
+    ///     performance.difficulty.flashlight_rating,
+    /// );
+    /// ```
+    #[inline]
+    pub fn aim_strain(&self) -> f64 {
+        self.difficulty.aim_strain
+    }
+
+    /// Return the speed strain of the difficulty attributes.
+    #[inline]
+    pub fn speed_strain(&self) -> f64 {
+        self.difficulty.speed_strain
+    }
+
+    /// Return the flashlight rating of the difficulty attributes.
+    #[inline]
+    pub fn flashlight_rating(&self) -> f64 {
+        self.difficulty.flashlight_rating
+    }
+
+    /// Return all attributes in a flat array e.g. for FFI purposes.
+    ///
+    /// The order of the values is fixed as follows:
+    ///
+    /// | Index | Value |
+    /// |-----|-----|
+    /// | 0 | `pp` |
+    /// | 1 | `pp_aim` |
+    /// | 2 | `pp_speed` |
+    /// | 3 | `pp_acc` |
+    /// | 4 | `pp_flashlight` |
+    /// | 5 | `stars` |
+    /// | 6 | `max_combo` |
+    /// | 7 | `aim_strain` |
+    /// | 8 | `speed_strain` |
+    /// | 9 | `flashlight_rating` |
+    /// | 10 | `slider_factor` |
+    /// | 11 | `ar` |
+    /// | 12 | `od` |
+    /// | 13 | `hp` |
+    /// | 14 | `cs` |
+    /// | 15 | `n_circles` |
+    /// | 16 | `n_sliders` |
+    /// | 17 | `n_spinners` |
+    #[inline]
+    pub fn as_array(&self) -> [f64; 18] {
+        let difficulty = &self.difficulty;
+
+        [
+            self.pp,
+            self.pp_aim,
+            self.pp_speed,
+            self.pp_acc,
+            self.pp_flashlight,
+            difficulty.stars,
+            difficulty.max_combo as f64,
+            difficulty.aim_strain,
+            difficulty.speed_strain,
+            difficulty.flashlight_rating,
+            difficulty.slider_factor,
+            difficulty.ar,
+            difficulty.od,
+            difficulty.hp,
+            difficulty.cs,
+            difficulty.n_circles as f64,
+            difficulty.n_sliders as f64,
+            difficulty.n_spinners as f64,
+        ]
+    }
+
+    /// Return how much more pp this score is worth compared to `no_mods`,
+    /// e.g. the result of [`OsuPP::calculate_nomod_baseline`].
+    #[inline]
+    pub fn pp_delta(&self, no_mods: &OsuPerformanceAttributes) -> f64 {
+        self.pp - no_mods.pp
+    }
+
+    /// Return the shares of aim, speed, accuracy, and flashlight in that order,
+    /// e.g. for breakdown charts.
+    ///
+    /// The shares are the skill values normalized to sum up to `1.0` before they are
+    /// combined through the power-mean, i.e. they are the pre-combination weights and
+    /// don't consider mod specific weighting such as [`OsuPP::rx_speed_weight`].
+    /// If all skill values are zero, all shares are zero too.
+    pub fn component_shares(&self) -> (f64, f64, f64, f64) {
+        let total = self.pp_aim + self.pp_speed + self.pp_acc + self.pp_flashlight;
+
+        if total <= 0.0 {
+            return (0.0, 0.0, 0.0, 0.0);
+        }
+
+        (
+            self.pp_aim / total,
+            self.pp_speed / total,
+            self.pp_acc / total,
+            self.pp_flashlight / total,
+        )
+    }
+
+    /// Return the pp and the accuracy between `0.0` and `1.0` of the score state
+    /// to sort scores by, e.g. for leaderboards.
+    ///
+    /// NaN values are replaced by negative infinity so that they sort last in
+    /// descending order and the keys can always be compared.
+    ///
+    /// ```
+    /// use rosu_pp::osu::OsuPerformanceAttributes;
+    ///
+    /// # let mut scores = vec![OsuPerformanceAttributes::default()];
+    /// # /*
+    /// let mut scores: Vec<OsuPerformanceAttributes> = ...
+    /// # */
+    ///
+    /// // Best score first
+    /// scores.sort_by(|a, b| b.sort_key().partial_cmp(&a.sort_key()).unwrap());
+    /// ```
+    pub fn sort_key(&self) -> (f64, f64) {
+        let state = &self.state;
+        let total_hits = state.n300 + state.n100 + state.n50 + state.misses;
+
+        let acc = if total_hits > 0 {
+            (state.n300 * 6 + state.n100 * 2 + state.n50) as f64 / (total_hits * 6) as f64
+        } else {
+            0.0
+        };
+
+        let not_nan = |value: f64| {
+            if value.is_nan() {
+                f64::NEG_INFINITY
+            } else {
+                value
+            }
+        };
+
+        (not_nan(self.pp), acc)
+    }
+
+    /// Recalculate the performance for a different set of mods, e.g. when toggling HD.
+    ///
+    /// The difficulty attributes, the score state, and the effective misses are reused
+    /// and only the pp formula is rerun. Since the difficulty can't be recalculated
+    /// without the map, `None` is returned if the mods differ in bits that affect
+    /// the difficulty, e.g. DT or FL.
+    ///
+    /// Calculator options that are not part of the attributes, e.g. the
+    /// [`ScoringMode`](crate::osu::ScoringMode) or beatmap specific relax multipliers,
+    /// are not known so their defaults are used.
+    #[inline]
+    pub fn recompute_visibility_mods(&self, new_mods: u32) -> Option<OsuPerformanceAttributes> {
+        pp::recalculate_with_mods(self, new_mods)
+    }
+
+    /// Recalculate the performance for a different accuracy, e.g. while dragging an
+    /// accuracy slider.
+    ///
+    /// The difficulty attributes, the combo, and the misses are kept while the
+    /// hitresults are generated anew for the accuracy like [`OsuPP::accuracy`] would.
+    ///
+    /// Since the difficulty is not recalculated, only the pp formula is rerun. The terms
+    /// that change with the accuracy or hitresults are:
+    /// - the accuracy value entirely
+    /// - the aim value's accuracy scaling, Blinds bonus, and slider nerf
+    /// - the speed value's accuracy scaling and 50s penalty
+    /// - the flashlight value's accuracy scaling
+    /// - the RX stream penalty and the aggregation of all values into the final pp
+    ///
+    /// The length bonus, miss penalties, effective misses, and quality multiplier stay
+    /// the same. Calculator options that are not part of the attributes, e.g. the
+    /// [`ScoringMode`](crate::osu::ScoringMode) or beatmap specific relax multipliers,
+    /// are not known so their defaults are used.
+    #[inline]
+    pub fn recalculate_accuracy(&self, new_acc: f64) -> OsuPerformanceAttributes {
+        pp::recalculate_accuracy(self, new_acc)
+    }
+
+    /// Determine how much HD changes each skill value of the score.
+    ///
+    /// The score is recalculated with and without HD like
+    /// [`recompute_visibility_mods`](OsuPerformanceAttributes::recompute_visibility_mods)
+    /// does and each skill value as well as the final pp are diffed.
+    ///
+    /// Since the skill values are aggregated through a power mean, their raw
+    /// differences don't sum up to the total pp difference. Hence, the total
+    /// is split across the skills in proportion to their raw differences.
+    pub fn hd_breakdown(&self) -> HdBreakdown {
+        // HD does not affect the difficulty so the attributes can always be reused
+        let with_hd = pp::recalculate_performance(self, self.mods | u32::HD);
+        let without_hd = pp::recalculate_performance(self, self.mods & !u32::HD);
+
+        let aim = with_hd.pp_aim - without_hd.pp_aim;
+        let speed = with_hd.pp_speed - without_hd.pp_speed;
+        let acc = with_hd.pp_acc - without_hd.pp_acc;
+        let flashlight = with_hd.pp_flashlight - without_hd.pp_flashlight;
+        let total = with_hd.pp - without_hd.pp;
+
+        let raw_sum = aim + speed + acc + flashlight;
+
+        if raw_sum.abs() <= f64::EPSILON {
+            return HdBreakdown {
+                total,
+                ..Default::default()
+            };
+        }
+
+        let scale = total / raw_sum;
+
+        HdBreakdown {
+            aim: aim * scale,
+            speed: speed * scale,
+            acc: acc * scale,
+            flashlight: flashlight * scale,
+            total,
+        }
+    }
+}
+
+/// How much HD changes each skill value of a score.
+///
+/// Obtained through [`OsuPerformanceAttributes::hd_breakdown`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HdBreakdown {
+    /// The share of the total difference that is attributed to the aim value.
+    pub aim: f64,
+    /// The share of the total difference that is attributed to the speed value.
+    pub speed: f64,
+    /// The share of the total difference that is attributed to the accuracy value.
+    pub acc: f64,
+    /// The share of the total difference that is attributed to the flashlight value.
+    pub flashlight: f64,
+    /// The total pp difference between the score with and without HD.
+    pub total: f64,
 }
 
 impl From<OsuPerformanceAttributes> for OsuDifficultyAttributes {
@@ -604,3 +1002,240 @@ impl From<OsuPerformanceAttributes> for OsuDifficultyAttributes {
 fn difficulty_range_od(od: f64) -> f64 {
     super::difficulty_range(od, 20.0, 50.0, 80.0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn map_max_combo() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        assert_eq!(map.max_combo(), OsuStars::new(&map).calculate().max_combo);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn component_shares() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let performance = OsuPP::new(&map).mods(8 + 1024).accuracy(98.5).calculate();
+
+        let (aim, speed, acc, flashlight) = performance.component_shares();
+
+        assert!((aim + speed + acc + flashlight - 1.0).abs() < 1e-9);
+        assert!(aim > 0.0 && speed > 0.0 && acc > 0.0 && flashlight > 0.0);
+
+        let empty = OsuPerformanceAttributes::default();
+        assert_eq!(empty.component_shares(), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn sort_key() {
+        let score = |pp: f64, n300: usize, n100: usize| OsuPerformanceAttributes {
+            pp,
+            state: OsuScoreState {
+                max_combo: 100,
+                n300,
+                n100,
+                n50: 0,
+                misses: 0,
+            },
+            ..Default::default()
+        };
+
+        let mut scores = [
+            score(f64::NAN, 100, 0),
+            score(200.0, 95, 5),
+            score(300.0, 90, 10),
+            score(200.0, 99, 1),
+            score(200.0, 95, 5),
+            score(f64::NAN, 90, 10),
+        ];
+
+        scores.sort_by(|a, b| b.sort_key().partial_cmp(&a.sort_key()).unwrap());
+
+        let expected = [
+            (300.0, 90),
+            (200.0, 99),
+            (200.0, 95),
+            (200.0, 95),
+            (f64::NEG_INFINITY, 100),
+            (f64::NEG_INFINITY, 90),
+        ];
+
+        for (score, (pp, n300)) in scores.iter().zip(expected.iter()) {
+            assert_eq!(score.sort_key().0, *pp);
+            assert_eq!(score.state.n300, *n300);
+        }
+
+        assert_eq!(OsuPerformanceAttributes::default().sort_key(), (0.0, 0.0));
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn from_path() {
+        let state = OsuScoreState {
+            max_combo: 800,
+            n300: 590,
+            n100: 9,
+            n50: 0,
+            misses: 2,
+        };
+
+        let performance = calculate_from_path("./maps/2785319.osu", 8, state.clone())
+            .expect("failed to parse map");
+
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let expected = OsuPP::new(&map).mods(8).state(state.clone()).calculate();
+
+        assert_eq!(performance, expected);
+        assert!(calculate_from_path("./maps/missing.osu", 8, state).is_err());
+    }
+
+    #[cfg(all(
+        feature = "parallel",
+        not(any(feature = "async_tokio", feature = "async_std"))
+    ))]
+    #[test]
+    fn calculate_many_matches_sequential() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let scores: Vec<_> = (0..40)
+            .map(|i| {
+                let state = OsuScoreState {
+                    max_combo: 909 - i * 10,
+                    n300: 601 - i - i % 3,
+                    n100: i,
+                    n50: 0,
+                    misses: i % 3,
+                };
+
+                (&map, [0, 8, 16, 64, 72][i % 5], state)
+            })
+            .collect();
+
+        let parallel = calculate_many(&scores);
+
+        let sequential: Vec<_> = scores
+            .iter()
+            .map(|(map, mods, state)| OsuPP::new(map).mods(*mods).state(state.clone()).calculate())
+            .collect();
+
+        assert_eq!(parallel, sequential);
+    }
+
+    #[cfg(feature = "async_tokio")]
+    #[test]
+    fn from_path_async_tokio() {
+        use tokio::runtime::Builder;
+
+        Builder::new_current_thread()
+            .build()
+            .expect("could not start runtime")
+            .block_on(async {
+                let performance =
+                    calculate_from_path("./maps/2785319.osu", 8, OsuScoreState::default()).await;
+
+                assert!(performance.is_ok());
+            });
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn hd_breakdown_deltas() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).mods(8).misses(1).accuracy(98.0);
+        let with_hd = calculator.clone().calculate();
+        let without_hd = calculator.clone().mods(0).calculate();
+
+        let breakdown = with_hd.hd_breakdown();
+
+        assert_eq!(breakdown.total, with_hd.pp - without_hd.pp);
+        assert!(breakdown.aim > 0.0 && breakdown.speed > 0.0 && breakdown.acc > 0.0);
+
+        let sum = breakdown.aim + breakdown.speed + breakdown.acc + breakdown.flashlight;
+        assert!((sum - breakdown.total).abs() < 1e-9);
+
+        // The shares keep the ratios of the raw skill differences
+        let aim = with_hd.pp_aim - without_hd.pp_aim;
+        let speed = with_hd.pp_speed - without_hd.pp_speed;
+        assert!((breakdown.aim / breakdown.speed - aim / speed).abs() < 1e-9);
+
+        // Without FL there is no flashlight value to begin with
+        assert_eq!(breakdown.flashlight, 0.0);
+
+        let calculator = OsuPP::new(&map).mods(8 + 1024).misses(1).accuracy(98.0);
+        let breakdown = calculator.calculate().hd_breakdown();
+        assert!(breakdown.flashlight > 0.0);
+
+        let sum = breakdown.aim + breakdown.speed + breakdown.acc + breakdown.flashlight;
+        assert!((sum - breakdown.total).abs() < 1e-9);
+    }
+
+    #[test]
+    fn performance_as_array() {
+        let attributes = OsuPerformanceAttributes {
+            difficulty: OsuDifficultyAttributes {
+                stars: 5.5,
+                max_combo: 1234,
+                n_spinners: 2,
+                ..Default::default()
+            },
+            pp: 321.0,
+            pp_aim: 123.0,
+            ..Default::default()
+        };
+
+        let array = attributes.as_array();
+
+        assert_eq!(array.len(), 18);
+        assert_eq!(array[0], attributes.pp);
+        assert_eq!(array[1], attributes.pp_aim);
+        assert_eq!(array[5], attributes.stars());
+        assert_eq!(array[6], 1234.0);
+        assert_eq!(array[17], 2.0);
+    }
+
+    #[cfg(all(
+        feature = "serde",
+        not(any(feature = "async_tokio", feature = "async_std"))
+    ))]
+    #[test]
+    fn serde_roundtrip() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let performance = OsuPP::new(&map)
+            .mods(8)
+            .misses(2)
+            .accuracy(97.5)
+            .calculate();
+
+        let json = serde_json::to_string(&performance).unwrap();
+        let deserialized: OsuPerformanceAttributes = serde_json::from_str(&json).unwrap();
+        assert_eq!(performance, deserialized);
+
+        let json = serde_json::to_string(&performance.difficulty).unwrap();
+        let attributes: OsuDifficultyAttributes = serde_json::from_str(&json).unwrap();
+
+        let recalculated = OsuPP::new(&map)
+            .attributes(attributes)
+            .mods(8)
+            .misses(2)
+            .accuracy(97.5)
+            .calculate();
+
+        assert_eq!(performance, recalculated);
+
+        let state = OsuScoreState {
+            max_combo: 500,
+            n300: 400,
+            n100: 20,
+            n50: 3,
+            misses: 2,
+        };
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(state, serde_json::from_str::<OsuScoreState>(&json).unwrap());
+    }
+}
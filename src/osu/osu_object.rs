@@ -27,6 +27,7 @@ pub(crate) enum OsuObjectKind {
         end_pos: Pos2,
         lazy_end_pos: Pos2,
         nested_objects: Vec<NestedObject>,
+        velocity: f64,
     },
     Spinner {
         end_time: f64,
@@ -55,6 +56,72 @@ pub(crate) struct ObjectParameters<'a> {
     pub(crate) curve_bufs: CurveBuffers,
 }
 
+/// Where the ticks of a slider are placed along its curve.
+///
+/// Shared by the nested object generation and [`Beatmap::max_combo`]
+/// so that both agree on the amount of nested objects.
+pub(crate) struct SliderTicks {
+    pub(crate) velocity: f64,
+    pub(crate) len: f64,
+    pub(crate) tick_dist: f64,
+    min_dist_from_end: f64,
+}
+
+impl SliderTicks {
+    pub(crate) fn new(map: &Beatmap, slider_state: &SliderState<'_>, curve: &Curve) -> Self {
+        let mut tick_dist = 100.0 * map.slider_mult / map.tick_rate;
+
+        // * prior to v8, speed multipliers don't adjust for how many ticks are generated over the same distance.
+        // * this results in more (or less) ticks being generated in <v8 maps for the same time duration.
+        if map.version >= 8 {
+            tick_dist /= (100.0 / slider_state.slider_velocity).clamp(10.0, 1000.0) / 100.0;
+        }
+
+        let velocity = (BASE_SCORING_DISTANCE * map.slider_mult * slider_state.slider_velocity)
+            / slider_state.beat_len;
+
+        // * A very lenient maximum length of a slider for ticks to be generated.
+        // * This exists for edge cases such as /b/1573664 where the beatmap has
+        // * been edited by the user, and should never be reached in normal usage.
+        let max_len = 100_000.0;
+
+        let len = curve.dist().min(max_len);
+
+        Self {
+            velocity,
+            len,
+            tick_dist: tick_dist.clamp(0.0, len),
+            min_dist_from_end: velocity * 10.0,
+        }
+    }
+
+    /// The distances along the curve at which the ticks of a single span are placed.
+    pub(crate) fn distances(&self) -> impl Iterator<Item = f64> {
+        let Self {
+            len,
+            tick_dist,
+            min_dist_from_end,
+            ..
+        } = *self;
+
+        let mut curr_dist = tick_dist;
+
+        std::iter::from_fn(move || {
+            (curr_dist < len - min_dist_from_end).then(|| {
+                let dist = curr_dist;
+                curr_dist += tick_dist;
+
+                dist
+            })
+        })
+    }
+
+    /// The amount of nested objects, i.e. the ticks of every span, the repeats, and the tail.
+    pub(crate) fn n_nested_objects(&self, repeats: usize) -> usize {
+        self.distances().count() * (repeats + 1) + repeats + 1
+    }
+}
+
 impl OsuObject {
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(h: &HitObject, hr: bool, params: &mut ObjectParameters<'_>) -> Option<Self> {
@@ -96,44 +163,31 @@ impl OsuObject {
 
                 let span_count = (*repeats + 1) as f64;
 
-                let mut tick_dist = 100.0 * map.slider_mult / map.tick_rate;
-
-                // * prior to v8, speed multipliers don't adjust for how many ticks are generated over the same distance.
-                // * this results in more (or less) ticks being generated in <v8 maps for the same time duration.
-                if map.version >= 8 {
-                    tick_dist /=
-                        (100.0 / slider_state.slider_velocity).max(10.0).min(1000.0) / 100.0;
-                }
-
                 // Build the curve w.r.t. the control points
                 let curve = Curve::new(control_points, *pixel_len, curve_bufs);
 
-                let velocity =
-                    (BASE_SCORING_DISTANCE * map.slider_mult * slider_state.slider_velocity)
-                        / slider_state.beat_len;
+                let slider_ticks = SliderTicks::new(map, slider_state, &curve);
+                let SliderTicks {
+                    velocity,
+                    len,
+                    tick_dist,
+                    ..
+                } = slider_ticks;
+
+                attributes.avg_slider_velocity +=
+                    (velocity - attributes.avg_slider_velocity) / attributes.n_sliders as f64;
 
                 let end_time = h.start_time + span_count * curve.dist() / velocity;
                 let duration = end_time - h.start_time;
                 let span_duration = duration / span_count;
 
-                // * A very lenient maximum length of a slider for ticks to be generated.
-                // * This exists for edge cases such as /b/1573664 where the beatmap has
-                // * been edited by the user, and should never be reached in normal usage.
-                let max_len = 100_000.0;
-
-                let len = curve.dist().min(max_len);
-                tick_dist = tick_dist.clamp(0.0, len);
-                let min_dist_from_end = velocity * 10.0;
-
-                let mut curr_dist = tick_dist;
-
                 ticks.clear();
                 ticks.reserve((len / tick_dist) as usize);
                 let mut nested_objects =
-                    Vec::with_capacity((len * span_count / tick_dist) as usize);
+                    Vec::with_capacity(slider_ticks.n_nested_objects(*repeats));
 
                 // Ticks of the first span
-                while curr_dist < len - min_dist_from_end {
+                for curr_dist in slider_ticks.distances() {
                     let progress = curr_dist / len;
 
                     let curr_time = h.start_time + progress * span_duration;
@@ -151,8 +205,6 @@ impl OsuObject {
 
                     nested_objects.push(tick);
                     ticks.push((curr_pos, curr_time));
-
-                    curr_dist += tick_dist;
                 }
 
                 // Other spans
@@ -281,6 +333,7 @@ impl OsuObject {
                         end_pos,
                         lazy_end_pos,
                         nested_objects,
+                        velocity,
                     },
                 }
             }
@@ -1,5 +1,36 @@
-use super::{OsuDifficultyAttributes, OsuPerformanceAttributes, OsuScoreState};
-use crate::{Beatmap, DifficultyAttributes, Mods, OsuStars, PerformanceAttributes};
+use std::collections::HashMap;
+
+use super::{
+    difficulty_range_od, score_report::mods_str, AccuracyMode, HitDistributionBias,
+    OsuBatchCalculator, OsuDifficultyAttributes, OsuGradualDifficultyAttributes,
+    OsuPerformanceAttributes, OsuScoreState, PpTrace, RelaxAccProfile, ScoreReport,
+    ScoreStateError, ScoringMode, SkillTrace,
+};
+
+use crate::{
+    math, Beatmap, DifficultyAttributes, GameMode, IntoMods, Mods, OsuStars, PerformanceAttributes,
+};
+
+/// Placeholder map for calculations that are based purely on attributes.
+static EMPTY_MAP: Beatmap = Beatmap {
+    mode: GameMode::STD,
+    version: 0,
+    n_circles: 0,
+    n_sliders: 0,
+    n_spinners: 0,
+    ar: 0.0,
+    od: 0.0,
+    cs: 0.0,
+    hp: 0.0,
+    slider_mult: 0.0,
+    tick_rate: 0.0,
+    hit_objects: Vec::new(),
+    sounds: Vec::new(),
+    timing_points: Vec::new(),
+    difficulty_points: Vec::new(),
+    stack_leniency: 0.0,
+    beatmap_id: 0,
+};
 
 /// Performance calculator on osu!standard maps.
 ///
@@ -35,16 +66,37 @@ use crate::{Beatmap, DifficultyAttributes, Mods, OsuStars, PerformanceAttributes
 pub struct OsuPP<'map> {
     map: &'map Beatmap,
     attributes: Option<OsuDifficultyAttributes>,
-    mods: u32,
+    pub(crate) mods: u32,
     acc: Option<f64>,
+    requested_acc: Option<f64>,
+    invalid_acc: Option<f64>,
     pub(crate) combo: Option<usize>,
 
     pub(crate) n300: Option<usize>,
     pub(crate) n100: Option<usize>,
     pub(crate) n50: Option<usize>,
     pub(crate) n_misses: usize,
+    derive_misses: bool,
+    slider_breaks: Option<usize>,
     pub(crate) passed_objects: Option<usize>,
     clock_rate: Option<f64>,
+    ar: Option<f64>,
+    od: Option<f64>,
+    quality_multiplier: f64,
+    global_multiplier: f64,
+    unstable_rate: Option<f64>,
+    acc_circle_bonus: (f64, f64),
+    rx_speed_weight: f64,
+    slider_velocity_bonus: f64,
+    difficult_slider_ratio: f64,
+    rx_acc_profile: RelaxAccProfile,
+    rx_ht_multipliers: (f64, f64),
+    rx_flashlight_combo_exponent: f64,
+    relax_nf_penalty: bool,
+    rx_overrides: HashMap<i32, f64>,
+    scoring_mode: ScoringMode,
+    hit_distribution_bias: HitDistributionBias,
+    accuracy_mode: AccuracyMode,
 }
 
 impl<'map> OsuPP<'map> {
@@ -56,23 +108,90 @@ impl<'map> OsuPP<'map> {
             attributes: None,
             mods: 0,
             acc: None,
+            requested_acc: None,
+            invalid_acc: None,
             combo: None,
 
             n300: None,
             n100: None,
             n50: None,
             n_misses: 0,
+            derive_misses: false,
+            slider_breaks: None,
             passed_objects: None,
             clock_rate: None,
+            ar: None,
+            od: None,
+            quality_multiplier: 1.0,
+            global_multiplier: 1.0,
+            unstable_rate: None,
+            acc_circle_bonus: (0.3, 1.15),
+            rx_speed_weight: 0.0,
+            slider_velocity_bonus: 0.0,
+            difficult_slider_ratio: 0.15,
+            rx_acc_profile: RelaxAccProfile::default(),
+            rx_ht_multipliers: (1.0, 1.0),
+            rx_flashlight_combo_exponent: 0.8,
+            relax_nf_penalty: true,
+            rx_overrides: HashMap::new(),
+            scoring_mode: ScoringMode::default(),
+            hit_distribution_bias: HitDistributionBias::default(),
+            accuracy_mode: AccuracyMode::default(),
         }
     }
 
+    /// Create a new performance calculator based only on difficulty attributes,
+    /// e.g. attributes that were stored previously, without requiring the map.
+    ///
+    /// The amount of objects is taken from the attributes' object counts.
+    /// Note that map specific adjustments based on the beatmap id as well as
+    /// [`passed_until_ms`](OsuPP::passed_until_ms) are not available.
+    #[inline]
+    pub fn from_attributes(attributes: OsuDifficultyAttributes) -> OsuPP<'static> {
+        let n_objects = attributes.n_circles + attributes.n_sliders + attributes.n_spinners;
+
+        OsuPP::new(&EMPTY_MAP)
+            .attributes(attributes)
+            .passed_objects(n_objects)
+    }
+
+    /// Create a calculator for many scores on the same map with the same mods.
+    ///
+    /// The difficulty attributes are calculated only once and reused for each score.
+    #[inline]
+    pub fn new_batch(map: &'map Beatmap, mods: u32) -> OsuBatchCalculator<'map> {
+        OsuBatchCalculator::new(map, mods)
+    }
+
+    /// Calculate the performance of a perfect play on the given map with the given mods,
+    /// i.e. 100% accuracy, a full combo, and no misses.
+    ///
+    /// The difficulty attributes are calculated only once. Relax and Autopilot are
+    /// treated like for any other score so their adjusted skill weighting applies.
+    pub fn max_pp(map: &'map Beatmap, mods: u32) -> OsuPerformanceAttributes {
+        let attributes = OsuStars::new(map).mods(mods).calculate();
+
+        OsuPP::new(map)
+            .mods(mods)
+            .combo(attributes.max_combo)
+            .n300(map.hit_objects.len())
+            .n100(0)
+            .n50(0)
+            .misses(0)
+            .attributes(attributes)
+            .calculate()
+    }
+
     /// Provide the result of a previous difficulty or performance calculation.
     /// If you already calculated the attributes for the current map-mod combination,
     /// be sure to put them in here so that they don't have to be recalculated.
+    ///
+    /// Invalid difficult strain counts, i.e. negative or NaN, are clamped to zero.
+    /// If the star rating is not finite, the attributes are recalculated instead.
     #[inline]
     pub fn attributes(mut self, attributes: impl OsuAttributeProvider) -> Self {
-        if let Some(attributes) = attributes.attributes() {
+        if let Some(mut attributes) = attributes.attributes() {
+            attributes.sanitize();
             self.attributes.replace(attributes);
         }
 
@@ -81,15 +200,19 @@ impl<'map> OsuPP<'map> {
 
     /// Specify mods through their bit values.
     ///
+    /// Either the raw bits or [`GameMods`](crate::GameMods) can be used.
+    ///
     /// See [https://github.com/ppy/osu-api/wiki#mods](https://github.com/ppy/osu-api/wiki#mods)
     #[inline]
-    pub fn mods(mut self, mods: u32) -> Self {
-        self.mods = mods;
+    pub fn mods(mut self, mods: impl IntoMods) -> Self {
+        self.mods = mods.into_bits();
 
         self
     }
 
     /// Specify the max combo of the play.
+    ///
+    /// Values above the map's max combo are clamped.
     #[inline]
     pub fn combo(mut self, combo: usize) -> Self {
         self.combo.replace(combo);
@@ -101,6 +224,8 @@ impl<'map> OsuPP<'map> {
     #[inline]
     pub fn n300(mut self, n300: usize) -> Self {
         self.n300.replace(n300);
+        self.acc = None;
+        self.invalid_acc = None;
 
         self
     }
@@ -109,6 +234,8 @@ impl<'map> OsuPP<'map> {
     #[inline]
     pub fn n100(mut self, n100: usize) -> Self {
         self.n100.replace(n100);
+        self.acc = None;
+        self.invalid_acc = None;
 
         self
     }
@@ -117,6 +244,8 @@ impl<'map> OsuPP<'map> {
     #[inline]
     pub fn n50(mut self, n50: usize) -> Self {
         self.n50.replace(n50);
+        self.acc = None;
+        self.invalid_acc = None;
 
         self
     }
@@ -125,6 +254,39 @@ impl<'map> OsuPP<'map> {
     #[inline]
     pub fn misses(mut self, n_misses: usize) -> Self {
         self.n_misses = n_misses;
+        self.derive_misses = false;
+        self.acc = None;
+        self.invalid_acc = None;
+
+        self
+    }
+
+    /// Specify the amount of misses of a play or explicitly leave it unset.
+    ///
+    /// If `None` is given and a combo was specified, [`accuracy`](OsuPP::accuracy)
+    /// estimates the amount of misses based on the combo instead of assuming zero misses.
+    #[inline]
+    pub fn n_misses(mut self, n_misses: Option<usize>) -> Self {
+        match n_misses {
+            Some(n_misses) => self.misses(n_misses),
+            None => {
+                self.n_misses = 0;
+                self.derive_misses = true;
+                self.acc = None;
+                self.invalid_acc = None;
+
+                self
+            }
+        }
+    }
+
+    /// Specify the exact amount of slider breaks of a play, e.g. counted from a replay.
+    ///
+    /// Slider breaks are usually guessed from the combo. If specified, the amount
+    /// is added to the misses directly and overrides that heuristic.
+    #[inline]
+    pub fn slider_breaks(mut self, slider_breaks: usize) -> Self {
+        self.slider_breaks = Some(slider_breaks);
 
         self
     }
@@ -141,9 +303,31 @@ impl<'map> OsuPP<'map> {
         self
     }
 
+    /// Amount of passed objects based on a timestamp in milliseconds, e.g. the time of a fail.
+    ///
+    /// All objects whose start time is at or before the timestamp count as passed.
+    /// The timestamp is adjusted by the clock rate so be sure to specify mods
+    /// and clock rate beforehand.
+    #[inline]
+    pub fn passed_until_ms(self, timestamp: f64) -> Self {
+        let map_time = timestamp * self.effective_clock_rate();
+
+        let passed_objects = self
+            .map
+            .hit_objects
+            .iter()
+            .take_while(|h| h.start_time <= map_time)
+            .count();
+
+        self.passed_objects(passed_objects)
+    }
+
     /// Adjust the clock rate used in the calculation.
     /// If none is specified, it will take the clock rate based on the mods
     /// i.e. 1.5 for DT, 0.75 for HT and 1.0 otherwise.
+    ///
+    /// Provided attributes that were calculated for a different clock rate
+    /// are detected through their OD and recalculated.
     #[inline]
     pub fn clock_rate(mut self, clock_rate: f64) -> Self {
         self.clock_rate = Some(clock_rate);
@@ -151,36 +335,288 @@ impl<'map> OsuPP<'map> {
         self
     }
 
+    /// Return the clock rate that the calculation will use, i.e. the specified
+    /// [`clock_rate`](OsuPP::clock_rate) or otherwise the one based on the mods.
+    #[inline]
+    pub fn effective_clock_rate(&self) -> f64 {
+        self.clock_rate.unwrap_or_else(|| self.mods.clock_rate())
+    }
+
+    /// Scale the final pp by a map specific quality factor e.g. to distinguish
+    /// between curated and loved maps. The value is clamped between `0.0` and `2.0`.
+    ///
+    /// If none is specified, it will default to `1.0`.
+    #[inline]
+    pub fn quality_multiplier(mut self, quality_multiplier: f64) -> Self {
+        self.quality_multiplier = quality_multiplier.clamp(0.0, 2.0);
+
+        self
+    }
+
+    /// Scale the final pp of all scores, e.g. to shift the whole pp curve.
+    /// It is applied after all penalties and map specific adjustments.
+    /// Negative values are treated as `0.0`.
+    ///
+    /// If none is specified, it will default to `1.0`.
+    #[inline]
+    pub fn global_multiplier(mut self, global_multiplier: f64) -> Self {
+        self.global_multiplier = global_multiplier.max(0.0);
+
+        self
+    }
+
+    /// Specify the unstable rate of a play.
+    ///
+    /// **Experimental**: If specified, the accuracy that scales the speed value
+    /// is refined by the estimated ratio of 300s based on the unstable rate
+    /// and the map's hit window, assuming normally distributed hit errors.
+    #[inline]
+    pub fn unstable_rate(mut self, unstable_rate: f64) -> Self {
+        self.unstable_rate = Some(unstable_rate.max(0.0));
+
+        self
+    }
+
+    /// Adjust the bonus of the accuracy value for maps with many circles.
+    ///
+    /// The bonus is calculated as `(n_circles / 1000) ^ exponent`, capped at `cap`.
+    /// If none is specified, it will default to an exponent of `0.3` and a cap of `1.15`.
+    #[inline]
+    pub fn acc_circle_bonus(mut self, exponent: f64, cap: f64) -> Self {
+        self.acc_circle_bonus = (exponent, cap);
+
+        self
+    }
+
+    /// Include the speed value with the given weight when aggregating the pp of RX scores.
+    ///
+    /// If none is specified, it will default to `0.0` i.e. the speed value is dropped for RX.
+    #[inline]
+    pub fn rx_speed_weight(mut self, weight: f64) -> Self {
+        self.rx_speed_weight = weight.max(0.0);
+
+        self
+    }
+
+    /// Reward maps with fast sliders by multiplying the aim value with
+    /// `1 + bonus * (avg_slider_velocity - 1)` for an average slider velocity
+    /// above one osu!pixel per millisecond.
+    ///
+    /// If none is specified, it will default to `0.0` i.e. no bonus.
+    #[inline]
+    pub fn slider_velocity_bonus(mut self, bonus: f64) -> Self {
+        self.slider_velocity_bonus = bonus.max(0.0);
+
+        self
+    }
+
+    /// Specify the ratio of sliders that are considered difficult, i.e. sliders
+    /// whose dropped ends nerf the aim value when the combo is not full.
+    ///
+    /// The ratio is clamped between `0.0` and `1.0`.
+    /// If none is specified, it will default to `0.15`.
+    #[inline]
+    pub fn difficult_slider_ratio(mut self, ratio: f64) -> Self {
+        self.difficult_slider_ratio = ratio.clamp(0.0, 1.0);
+
+        self
+    }
+
+    /// Specify whether the score was set with the accounting of stable or lazer.
+    ///
+    /// In [`ScoringMode::Lazer`], slider tails count toward the accuracy and dropped
+    /// slider tails are estimated from the combo alone since they don't turn 300s into 100s.
+    /// The accuracy given through [`accuracy`](OsuPP::accuracy) is still treated as the
+    /// accuracy of the hitresults without slider tails.
+    ///
+    /// If none is specified, it will default to [`ScoringMode::Stable`].
+    #[inline]
+    pub fn scoring_mode(mut self, mode: ScoringMode) -> Self {
+        self.scoring_mode = mode;
+
+        self
+    }
+
+    /// Specify how [`accuracy`](OsuPP::accuracy) distributes hitresults if none
+    /// were set beforehand. Be sure to set it before calling `accuracy`.
+    ///
+    /// If none is specified, it will default to [`HitDistributionBias::FewerFifties`].
+    #[inline]
+    pub fn hit_distribution_bias(mut self, bias: HitDistributionBias) -> Self {
+        self.hit_distribution_bias = bias;
+
+        self
+    }
+
+    /// Specify how accuracy is weighted, both for [`accuracy`](OsuPP::accuracy)
+    /// generating hitresults and for the accuracy used in the calculation.
+    /// Be sure to set it before calling `accuracy`.
+    ///
+    /// If none is specified, it will default to [`AccuracyMode::Standard`].
+    #[inline]
+    pub fn accuracy_mode(mut self, mode: AccuracyMode) -> Self {
+        self.accuracy_mode = mode;
+
+        self
+    }
+
+    /// Adjust the accuracy value of RX scores through a [`RelaxAccProfile`].
+    ///
+    /// Scores without RX are not affected.
+    /// If none is specified, the accuracy value is not adjusted.
+    #[inline]
+    pub fn relax_acc_profile(mut self, profile: RelaxAccProfile) -> Self {
+        self.rx_acc_profile = profile;
+
+        self
+    }
+
+    /// Multiply the aim and accuracy values of scores with both RX and HT,
+    /// e.g. to account for the easier reading of slowed down maps.
+    ///
+    /// Only the HT specific weighting is adjusted; the clock rate is applied as usual.
+    /// If none is specified, it will default to `1.0` for both i.e. no adjustment.
+    #[inline]
+    pub fn rx_ht_multipliers(mut self, aim: f64, acc: f64) -> Self {
+        self.rx_ht_multipliers = (aim.max(0.0), acc.max(0.0));
+
+        self
+    }
+
+    /// Adjust the exponent of the flashlight value's combo scaling for scores with RX and FL.
+    ///
+    /// Apart from that, the flashlight value of RX scores is calculated just like
+    /// without RX since RX doesn't help with reading the map. With the cursor being the
+    /// only input, combo breaks are more telling of the reading difficulty though so a
+    /// higher exponent punishes them more.
+    /// If none is specified, it will default to `0.8`, the exponent without RX.
+    #[inline]
+    pub fn rx_flashlight_combo_exponent(mut self, exponent: f64) -> Self {
+        self.rx_flashlight_combo_exponent = exponent.max(0.0);
+
+        self
+    }
+
+    /// Specify whether the NF penalty applies to scores with RX.
+    ///
+    /// Many servers consider NF meaningless for RX and don't want to penalize it.
+    /// Scores without RX always receive the NF penalty.
+    /// If none is specified, it will default to `true`, i.e. RX scores are penalized too.
+    #[inline]
+    pub fn relax_nf_penalty(mut self, penalize: bool) -> Self {
+        self.relax_nf_penalty = penalize;
+
+        self
+    }
+
+    /// Override the approach rate that the pp formulas use, e.g. for setups with custom
+    /// difficulty settings. The value is clamped between `0.0` and `11.0`.
+    ///
+    /// Unlike [`Beatmap::with_difficulty`], the difficulty calculation is not affected.
+    /// Only the AR bonuses are adjusted and the difficulty attributes of the result
+    /// contain the overridden value.
+    #[inline]
+    pub fn ar(mut self, ar: f64) -> Self {
+        self.ar = Some(ar.clamp(0.0, 11.0));
+
+        self
+    }
+
+    /// Override the overall difficulty that the pp formulas use, e.g. for setups with
+    /// custom difficulty settings. The value is clamped between `0.0` and `10.0`.
+    ///
+    /// Unlike [`Beatmap::with_difficulty`], the difficulty calculation is not affected.
+    /// Only the OD scaling of the skill values is adjusted and the difficulty attributes
+    /// of the result contain the overridden value.
+    #[inline]
+    pub fn od(mut self, od: f64) -> Self {
+        self.od = Some(od.clamp(0.0, 10.0));
+
+        self
+    }
+
+    /// Specify pp multipliers of RX scores for individual maps through their beatmap id.
+    ///
+    /// Overrides take precedence over the built-in multipliers of specific maps.
+    #[inline]
+    pub fn with_rx_overrides(mut self, overrides: HashMap<i32, f64>) -> Self {
+        self.rx_overrides = overrides;
+
+        self
+    }
+
     /// Provide parameters through an [`OsuScoreState`].
     #[inline]
     pub fn state(mut self, state: OsuScoreState) -> Self {
-        let OsuScoreState {
-            max_combo,
-            n300,
-            n100,
-            n50,
-            misses,
-        } = state;
-
-        self.combo = Some(max_combo);
-        self.n300 = Some(n300);
-        self.n100 = Some(n100);
-        self.n50 = Some(n50);
-        self.n_misses = misses;
+        self.set_state(&state);
 
         self
     }
 
+    pub(crate) fn set_state(&mut self, state: &OsuScoreState) {
+        self.combo = Some(state.max_combo);
+        self.n300 = Some(state.n300);
+        self.n100 = Some(state.n100);
+        self.n50 = Some(state.n50);
+        self.n_misses = state.misses;
+        self.derive_misses = false;
+        self.acc = None;
+        self.invalid_acc = None;
+    }
+
     /// Generate the hit results with respect to the given accuracy between `0` and `100`.
     ///
+    /// Values outside of that range are clamped. Use [`try_calculate`](OsuPP::try_calculate)
+    /// to get an error for them instead.
+    ///
     /// Be sure to set `misses` beforehand!
     /// In case of a partial play, be also sure to set `passed_objects` beforehand!
+    ///
+    /// Setting any hit result afterwards discards the generated accuracy
+    /// so that it will be recalculated based on the hit results instead.
+    ///
+    /// If only `n300` was set beforehand, its value is kept and the
+    /// remaining objects are distributed across n100s and n50s.
+    ///
+    /// If `n100` or `n50` were set beforehand, the remaining objects are distributed
+    /// across n300s and n50s, possibly adjusting the given values to get closer to
+    /// the accuracy. Use [`fill_n300_from_accuracy`](OsuPP::fill_n300_from_accuracy)
+    /// to keep them fixed instead.
+    ///
+    /// If no hitresults were set beforehand, they are distributed according to
+    /// the [`hit_distribution_bias`](OsuPP::hit_distribution_bias).
+    ///
+    /// If misses were left unset through [`n_misses`](OsuPP::n_misses)
+    /// and a combo was specified, the misses are estimated based on the combo.
+    ///
+    /// The accuracy is interpreted according to the [`accuracy_mode`](OsuPP::accuracy_mode).
     pub fn accuracy(mut self, acc: f64) -> Self {
         let n_objects = self
             .passed_objects
             .unwrap_or_else(|| self.map.hit_objects.len());
 
-        let mut acc = acc / 100.0;
+        if let Some(combo) = self.combo.filter(|_| self.derive_misses) {
+            self.n_misses = self.estimate_misses(combo).min(n_objects);
+        }
+
+        self.requested_acc = Some(acc);
+        self.invalid_acc = (!(0.0..=100.0).contains(&acc)).then_some(acc);
+
+        let mut acc = if acc.is_nan() {
+            0.0
+        } else {
+            acc.clamp(0.0, 100.0) / 100.0
+        };
+
+        // Points that the object hitresults need to make up for
+        let target_points = {
+            let (n_heads, heads_hit) = self
+                .accuracy_mode
+                .slider_heads(self.passed_sliders(n_objects), self.n_misses);
+
+            ((6.0 * acc * (n_objects + n_heads) as f64).round() as usize)
+                .saturating_sub(6 * heads_hit)
+        };
 
         if self.n100.or(self.n50).is_some() {
             let mut n100 = self.n100.unwrap_or(0);
@@ -188,8 +624,7 @@ impl<'map> OsuPP<'map> {
 
             let placed_points = 2 * n100 + n50 + self.n_misses;
             let missing_objects = n_objects - n100 - n50 - self.n_misses;
-            let missing_points =
-                ((6.0 * acc * n_objects as f64).round() as usize).saturating_sub(placed_points);
+            let missing_points = target_points.saturating_sub(placed_points);
 
             let mut n300 = missing_objects.min(missing_points / 6);
             n50 += missing_objects - n300;
@@ -208,21 +643,56 @@ impl<'map> OsuPP<'map> {
             self.n100 = Some(n100);
             self.n50 = Some(n50);
 
+            acc = (6 * n300 + 2 * n100 + n50) as f64 / (6 * n_objects) as f64;
+        } else if let Some(n300) = self.n300 {
+            let misses = self.n_misses.min(n_objects);
+            let n300 = n300.min(n_objects - misses);
+            let remaining = n_objects - n300 - misses;
+
+            // Distribute the remaining points across n100s and n50s
+            let missing_points = target_points.saturating_sub(6 * n300);
+
+            let n100 = missing_points.saturating_sub(remaining).min(remaining);
+            let n50 = remaining - n100;
+
+            self.n300 = Some(n300);
+            self.n100 = Some(n100);
+            self.n50 = Some(n50);
+
             acc = (6 * n300 + 2 * n100 + n50) as f64 / (6 * n_objects) as f64;
         } else {
             let misses = self.n_misses.min(n_objects);
-            let target_total = (acc * n_objects as f64 * 6.0).round() as usize;
-            let delta = target_total - (n_objects - misses);
+            let target_total = target_points;
+
+            let (n300, n100, n50) = match self.hit_distribution_bias {
+                HitDistributionBias::FewerFifties => {
+                    let delta = target_total.saturating_sub(n_objects - misses);
 
-            let mut n300 = delta / 5;
-            let mut n100 = (delta % 5).min(n_objects - n300 - misses);
-            let mut n50 = n_objects - n300 - n100 - misses;
+                    let mut n300 = (delta / 5).min(n_objects - misses);
+                    let mut n100 = (delta % 5).min(n_objects - n300 - misses);
+                    let mut n50 = n_objects - n300 - n100 - misses;
 
-            // Sacrifice n300s to transform n50s into n100s
-            let n = n300.min(n50 / 4);
-            n300 -= n;
-            n100 += 5 * n;
-            n50 -= 4 * n;
+                    // Sacrifice n300s to transform n50s into n100s
+                    let n = n300.min(n50 / 4);
+                    n300 -= n;
+                    n100 += 5 * n;
+                    n50 -= 4 * n;
+
+                    (n300, n100, n50)
+                }
+                HitDistributionBias::Realistic => {
+                    let n_hits = n_objects - misses;
+                    let missing_points = (6 * n_hits).saturating_sub(target_total);
+
+                    // Every 300 that turns into a 100 loses 4 points, into a 50 loses 5
+                    // points. Ten 100s and one 50 lose 45 points together.
+                    let n50 = (missing_points / 45).min(n_hits / 11);
+                    let n100 = (10 * n50 + (missing_points - 45 * n50) / 4).min(n_hits - n50);
+                    let n300 = n_hits - n100 - n50;
+
+                    (n300, n100, n50)
+                }
+            };
 
             self.n300 = Some(n300);
             self.n100 = Some(n100);
@@ -236,7 +706,150 @@ impl<'map> OsuPP<'map> {
         self
     }
 
-    fn assert_hitresults(self, attributes: OsuDifficultyAttributes) -> OsuPPInner {
+    /// The amount of sliders among the passed objects.
+    fn passed_sliders(&self, n_objects: usize) -> usize {
+        match self.attributes {
+            Some(ref attributes) => attributes.n_sliders,
+            None => self
+                .map
+                .hit_objects
+                .iter()
+                .take(n_objects)
+                .filter(|h| h.is_slider())
+                .count(),
+        }
+    }
+
+    /// Solve for the amount of 300s so that together with the previously set
+    /// `n100`, `n50`, and `misses` the given accuracy between `0` and `100` is reached.
+    ///
+    /// Unlike [`accuracy`](OsuPP::accuracy), the given hitresults are kept as they are.
+    /// The amount of 300s is rounded to the closest value.
+    ///
+    /// Returns an error if the accuracy is out of bounds, if it can't be reached
+    /// with the given hitresults, or if the resulting hitresults exceed the amount
+    /// of objects. Note that for partial plays with fewer hitresults than objects,
+    /// `passed_objects` should be set accordingly.
+    pub fn fill_n300_from_accuracy(mut self, acc: f64) -> Result<Self, ScoreStateError> {
+        if !(0.0..=100.0).contains(&acc) {
+            return Err(ScoreStateError::InvalidAccuracy(acc));
+        }
+
+        let n_objects = self.passed_objects.unwrap_or(self.map.hit_objects.len());
+
+        let n100 = self.n100.unwrap_or(0);
+        let n50 = self.n50.unwrap_or(0);
+        let misses = self.n_misses;
+        let others = n100 + n50 + misses;
+
+        let n300 = if others == 0 {
+            // Only 300s so any amount results in 100%
+            if acc < 100.0 {
+                return Err(ScoreStateError::UnreachableAccuracy(acc));
+            }
+
+            n_objects
+        } else {
+            if acc >= 100.0 {
+                return Err(ScoreStateError::UnreachableAccuracy(acc));
+            }
+
+            // Solve acc = (6 * n300 + 2 * n100 + n50) / (6 * (n300 + others)) for n300
+            let ratio = acc / 100.0;
+            let n300 =
+                (6.0 * ratio * others as f64 - (2 * n100 + n50) as f64) / (6.0 * (1.0 - ratio));
+
+            if n300 < -0.5 {
+                return Err(ScoreStateError::UnreachableAccuracy(acc));
+            }
+
+            n300.round().max(0.0) as usize
+        };
+
+        let total = n300 + others;
+
+        if total > n_objects {
+            return Err(ScoreStateError::TooManyHitResults { total, n_objects });
+        }
+
+        self.n300 = Some(n300);
+        self.n100 = Some(n100);
+        self.n50 = Some(n50);
+        self.derive_misses = false;
+        self.acc = None;
+        self.invalid_acc = None;
+
+        Ok(self)
+    }
+
+    /// Generate the hit results from a measured distribution of hit errors,
+    /// e.g. based on the cursor data of a relax score.
+    ///
+    /// Hit errors are assumed to be normally distributed with the given mean in
+    /// milliseconds and a standard deviation of `unstable_rate / 10`. The probability
+    /// of an object to land within a hit window `w` is `P(-w <= error <= w)` and the
+    /// windows for 300s, 100s, and 50s are derived from the clock rate adjusted OD.
+    /// Since misses are specified separately, the remaining objects are distributed
+    /// across 300s, 100s, and 50s with respect to the probabilities conditioned on
+    /// hitting the object at all.
+    ///
+    /// Be sure to set `misses` and, in case of a partial play, `passed_objects` beforehand!
+    /// The unstable rate is not used for anything else, see
+    /// [`unstable_rate`](OsuPP::unstable_rate) to also refine the speed value.
+    pub fn hit_window_accuracy(mut self, mean_error_ms: f64, unstable_rate: f64) -> Self {
+        let attributes = self.take_attributes();
+        let od = attributes.od;
+        self.attributes = Some(attributes);
+
+        let n_objects = self.passed_objects.unwrap_or(self.map.hit_objects.len());
+        let n_hits = n_objects.saturating_sub(self.n_misses);
+
+        let deviation = unstable_rate.max(0.0) / 10.0;
+        let p300 = hit_probability(80.0 - 6.0 * od, mean_error_ms, deviation);
+        let p100 = hit_probability(140.0 - 8.0 * od, mean_error_ms, deviation);
+        let p50 = hit_probability(200.0 - 10.0 * od, mean_error_ms, deviation);
+
+        let (n300, n100) = if p50 > 0.0 {
+            let n300 = (n_hits as f64 * p300 / p50).round() as usize;
+            let n100 = (n_hits as f64 * (p100 - p300) / p50).round() as usize;
+
+            (n300.min(n_hits), n100.min(n_hits - n300.min(n_hits)))
+        } else {
+            (0, 0)
+        };
+
+        self.n300 = Some(n300);
+        self.n100 = Some(n100);
+        self.n50 = Some(n_hits - n300 - n100);
+        self.derive_misses = false;
+        self.acc = None;
+        self.invalid_acc = None;
+
+        self
+    }
+
+    /// Return the hit results that the current parameters resolve to,
+    /// i.e. the hit results that would be used for the calculation.
+    ///
+    /// If no combo was specified, the map's max combo is used.
+    pub fn generate_state(&self) -> OsuScoreState {
+        let (_, n300, n100, n50) = self.resolve_hitresults();
+
+        let max_combo = match self.attributes {
+            Some(ref attributes) => attributes.max_combo,
+            None => self.map.max_combo(),
+        };
+
+        OsuScoreState {
+            max_combo: self.combo.map_or(max_combo, |combo| combo.min(max_combo)),
+            n300,
+            n100,
+            n50,
+            misses: self.n_misses,
+        }
+    }
+
+    fn resolve_hitresults(&self) -> (f64, usize, usize, usize) {
         let mut n300 = self.n300;
         let mut n100 = self.n100;
         let mut n50 = self.n50;
@@ -250,27 +863,8 @@ impl<'map> OsuPP<'map> {
             let n100 = n100.unwrap_or(0);
             let n50 = n50.unwrap_or(0);
 
-            let total_hits = (n300 + n100 + n50 + self.n_misses).min(n_objects) as f64;
-
-            let effective_misses =
-                calculate_effective_misses(&attributes, self.combo, self.n_misses, total_hits);
-
-            OsuPPInner {
-                attributes,
-                mods: self.mods,
-                combo: self.combo,
-                acc,
-                n300,
-                n100,
-                n50,
-                total_hits,
-                effective_misses,
-            }
+            (acc, n300, n100, n50)
         } else {
-            let n_objects = self
-                .passed_objects
-                .unwrap_or_else(|| self.map.hit_objects.len());
-
             let remaining = n_objects
                 .saturating_sub(n300.unwrap_or(0))
                 .saturating_sub(n100.unwrap_or(0))
@@ -303,185 +897,840 @@ impl<'map> OsuPP<'map> {
                 0.0
             };
 
-            let total_hits = (n300 + n100 + n50 + self.n_misses).min(n_objects) as f64;
-
-            let effective_misses =
-                calculate_effective_misses(&attributes, self.combo, self.n_misses, total_hits);
-
-            OsuPPInner {
-                attributes,
-                mods: self.mods,
-                combo: self.combo,
-                acc,
-                n300,
-                n100,
-                n50,
-                total_hits,
-                effective_misses,
-            }
+            (acc, n300, n100, n50)
         }
     }
 
-    /// Calculate all performance related values, including pp and stars.
-    pub fn calculate(mut self) -> OsuPerformanceAttributes {
-        let attributes = self.attributes.take().unwrap_or_else(|| {
-            let mut calculator = OsuStars::new(self.map).mods(self.mods);
+    fn assert_hitresults(&self, mut attributes: OsuDifficultyAttributes) -> OsuPPInner<'_> {
+        if let Some(ar) = self.ar {
+            attributes.ar = ar;
+        }
 
-            if let Some(passed_objects) = self.passed_objects {
-                calculator = calculator.passed_objects(passed_objects);
-            }
+        if let Some(od) = self.od {
+            attributes.od = od;
+        }
 
-            if let Some(clock_rate) = self.clock_rate {
-                calculator = calculator.clock_rate(clock_rate);
-            }
+        let n_objects = self.passed_objects.unwrap_or(self.map.hit_objects.len());
 
-            calculator.calculate()
-        });
+        let (acc, n300, n100, n50) = self.resolve_hitresults();
 
-        let id = self.map.beatmap_id.clone();
-        self.assert_hitresults(attributes).calculate(&id)
-    }
-}
+        // Excess hitresults are clamped through `total_hits` but the counts should
+        // never sum up to more than the passed objects; only checked in debug builds
+        debug_assert!(
+            n300 + n100 + n50 + self.n_misses <= n_objects,
+            "more hitresults than objects: {} > {}",
+            n300 + n100 + n50 + self.n_misses,
+            n_objects,
+        );
 
-struct OsuPPInner {
-    attributes: OsuDifficultyAttributes,
-    mods: u32,
-    acc: f64,
-    combo: Option<usize>,
+        let total_hits = (n300 + n100 + n50 + self.n_misses).min(n_objects) as f64;
 
-    n300: usize,
-    n100: usize,
-    n50: usize,
+        // Combos beyond the map's max combo are invalid
+        let combo = self.combo.map(|combo| combo.min(attributes.max_combo));
 
-    total_hits: f64,
-    effective_misses: usize,
-}
+        let effective_misses = match self.slider_breaks {
+            Some(slider_breaks) => ((self.n_misses + slider_breaks) as f64)
+                .min(total_hits)
+                .max(self.n_misses as f64),
+            None => calculate_effective_misses(&attributes, combo, self.n_misses, total_hits),
+        };
 
-impl OsuPPInner {
-    fn calculate(self, map_id: &i32) -> OsuPerformanceAttributes {
-        let (aim_value, speed_value, acc_value, flashlight_value, pp) =
-            if self.total_hits.abs() <= f64::EPSILON {
-                (0.0, 0.0, 0.0, 0.0, 0.0)
-            } else {
-                let mut multiplier = 1.12;
+        // The accuracy of the resolved hitresults must be a valid fraction
+        debug_assert!((0.0..=1.0).contains(&acc), "accuracy out of range: {}", acc);
 
-                // NF penalty
-                if self.mods.nf() {
-                    multiplier *= (1.0 - 0.02 * (self.effective_misses as f64)).max(0.9);
-                }
+        let missing_combo = attributes.max_combo - combo.unwrap_or(attributes.max_combo);
 
-                // SO penalty
-                if self.mods.so() {
-                    let n_spinners = self.attributes.n_spinners;
-                    multiplier *= 1.0 - (n_spinners as f64 / self.total_hits).powf(0.85);
-                }
+        let slider_ends_dropped = match self.scoring_mode {
+            // * A dropped slider end turns a 300 into a 100
+            ScoringMode::Stable => (total_hits - n300 as f64).min(missing_combo as f64),
+            ScoringMode::Lazer => missing_combo.min(attributes.n_sliders) as f64,
+        };
 
-                let mut aim_value = self.compute_aim_value();
-                let speed_value = self.compute_speed_value();
-                let acc_value = self.compute_accuracy_value();
-                let flashlight_value = self.compute_flashlight_value();
+        let mut numerator = acc * 6.0 * n_objects as f64;
+        let mut denominator = 6.0 * n_objects as f64;
+
+        // Slider heads are worth a full 300 with ScoreV2
+        let (n_heads, heads_hit) = self
+            .accuracy_mode
+            .slider_heads(attributes.n_sliders, self.n_misses);
+        numerator += 6.0 * heads_hit as f64;
+        denominator += 6.0 * n_heads as f64;
+
+        if self.scoring_mode == ScoringMode::Lazer {
+            // Slider tails are worth half of a 300
+            let n_sliders = attributes.n_sliders as f64;
+            numerator += 3.0 * (n_sliders - slider_ends_dropped);
+            denominator += 3.0 * n_sliders;
+        }
 
-                // RX stream penalty
-                if self.mods.rx() {
-                    let stream_factor = aim_value / speed_value;
+        let acc = if denominator > 0.0 {
+            numerator / denominator
+        } else {
+            acc
+        };
 
-                    if stream_factor < 1.0 {
-                        let depression_factor = if self.acc >= 0.97 {
-                            0.92 - ((1.0 - self.acc.ceil()) * 1.5)
-                        } else {
-                            0.85
-                        };
+        let state = OsuScoreState {
+            max_combo: combo.unwrap_or(attributes.max_combo),
+            n300,
+            n100,
+            n50,
+            misses: self.n_misses,
+        };
 
-                        aim_value *= depression_factor;
-                    }
+        OsuPPInner {
+            attributes,
+            mods: self.mods,
+            combo,
+            acc,
+            n300,
+            n100,
+            n50,
+            total_hits,
+            effective_misses,
+            slider_ends_dropped,
+            quality_multiplier: self.quality_multiplier,
+            global_multiplier: self.global_multiplier,
+            unstable_rate: self.unstable_rate,
+            acc_circle_bonus: self.acc_circle_bonus,
+            rx_speed_weight: self.rx_speed_weight,
+            slider_velocity_bonus: self.slider_velocity_bonus,
+            difficult_slider_ratio: self.difficult_slider_ratio,
+            rx_acc_profile: self.rx_acc_profile,
+            rx_ht_multipliers: self.rx_ht_multipliers,
+            rx_flashlight_combo_exponent: self.rx_flashlight_combo_exponent,
+            relax_nf_penalty: self.relax_nf_penalty,
+            rx_overrides: &self.rx_overrides,
+            state,
+        }
+    }
+
+    /// Calculate all performance related values, including pp and stars.
+    #[inline]
+    pub fn calculate(self) -> OsuPerformanceAttributes {
+        self.calculate_with_trace().0
+    }
+
+    /// Same as [`calculate`](OsuPP::calculate) but instead of clamping invalid values,
+    /// an error is returned if the mods are mutually exclusive, the hitresults exceed
+    /// the amount of objects, the combo exceeds the map's max combo, or the accuracy
+    /// given to [`accuracy`](OsuPP::accuracy) is not between `0` and `100`.
+    pub fn try_calculate(mut self) -> Result<OsuPerformanceAttributes, ScoreStateError> {
+        if self.mods.sanitize_mods() != self.mods {
+            return Err(ScoreStateError::IncompatibleMods(self.mods));
+        }
+
+        let attributes = self.take_attributes();
+        let n_objects = self.passed_objects.unwrap_or(self.map.hit_objects.len());
+
+        let total =
+            self.n300.unwrap_or(0) + self.n100.unwrap_or(0) + self.n50.unwrap_or(0) + self.n_misses;
+
+        if total > n_objects {
+            return Err(ScoreStateError::TooManyHitResults { total, n_objects });
+        }
+
+        if let Some(combo) = self.combo.filter(|&combo| combo > attributes.max_combo) {
+            return Err(ScoreStateError::ComboTooHigh {
+                combo,
+                max_combo: attributes.max_combo,
+            });
+        }
+
+        if let Some(acc) = self.invalid_acc {
+            return Err(ScoreStateError::InvalidAccuracy(acc));
+        }
+
+        self.attributes = Some(attributes);
+
+        Ok(self.calculate())
+    }
+
+    /// Same as [`calculate`](OsuPP::calculate) but additionally returns a [`PpTrace`]
+    /// containing the intermediate values of the calculation.
+    ///
+    /// Suitable to explain how the final pp value came to be.
+    pub fn calculate_with_trace(mut self) -> (OsuPerformanceAttributes, PpTrace) {
+        let attributes = self.take_attributes();
+
+        self.assert_hitresults(attributes)
+            .calculate(&self.map.beatmap_id)
+    }
+
+    /// Calculate only the pp based on the given attributes, neither consuming
+    /// the calculator nor collecting the full performance attributes.
+    pub(crate) fn calculate_pp_with(&self, attributes: &OsuDifficultyAttributes) -> f64 {
+        self.assert_hitresults(attributes.clone())
+            .calculate_pp(&self.map.beatmap_id)
+    }
+
+    /// Calculate the performance if the current score was a full combo.
+    ///
+    /// The 100s and 50s are kept as they are while misses are turned into 300s
+    /// and the combo is set to the map's max combo. Specified slider breaks are
+    /// discarded as well. Previously provided difficulty attributes are reused.
+    pub fn if_fc(mut self) -> OsuPerformanceAttributes {
+        let attributes = self.take_attributes();
+        let current = self.assert_hitresults(attributes.clone());
+        let (n300, n100, n50) = (current.n300, current.n100, current.n50);
+
+        self.n300 = Some(n300 + self.n_misses);
+        self.n100 = Some(n100);
+        self.n50 = Some(n50);
+        self.n_misses = 0;
+        self.derive_misses = false;
+        self.slider_breaks = None;
+        self.acc = None;
+        self.invalid_acc = None;
+        self.combo = Some(attributes.max_combo);
+        self.attributes = Some(attributes);
+
+        self.calculate()
+    }
+
+    /// Calculate the performance and collect all relevant values of the score
+    /// into a [`ScoreReport`].
+    pub fn report(mut self) -> ScoreReport {
+        let attributes = self.take_attributes();
+
+        let beatmap_id = self.map.beatmap_id;
+        let n_misses = self.n_misses;
+        let inner = self.assert_hitresults(attributes);
+
+        let mods = inner.mods;
+        let accuracy = 100.0 * inner.acc;
+        let (n300, n100, n50) = (inner.n300, inner.n100, inner.n50);
+        let combo = inner.combo;
+
+        let (result, _) = inner.calculate(&beatmap_id);
+        let difficulty = &result.difficulty;
+
+        ScoreReport {
+            beatmap_id,
+            mods,
+            mods_str: mods_str(mods),
+            stars: difficulty.stars,
+            pp: result.pp,
+            pp_aim: result.pp_aim,
+            pp_speed: result.pp_speed,
+            pp_acc: result.pp_acc,
+            pp_flashlight: result.pp_flashlight,
+            accuracy,
+            combo: combo.unwrap_or(difficulty.max_combo),
+            max_combo: difficulty.max_combo,
+            n300,
+            n100,
+            n50,
+            n_misses,
+            ar: difficulty.ar,
+            od: difficulty.od,
+            cs: difficulty.cs,
+            hp: difficulty.hp,
+        }
+    }
+
+    fn take_attributes(&mut self) -> OsuDifficultyAttributes {
+        match self.attributes.take() {
+            // Without hit objects there is nothing to recalculate from
+            Some(attributes) if self.map.hit_objects.is_empty() => attributes,
+            // Attributes with a non-finite star rating are considered corrupt
+            Some(attributes)
+                if attributes.stars.is_finite() && !self.mismatches_clock_rate(&attributes) =>
+            {
+                attributes
+            }
+            _ => self.calculate_difficulty(),
+        }
+    }
+
+    /// Whether the attributes' OD was calculated for a different clock rate
+    /// than the one that was specified.
+    fn mismatches_clock_rate(&self, attributes: &OsuDifficultyAttributes) -> bool {
+        let clock_rate = match self.clock_rate {
+            Some(clock_rate) => clock_rate,
+            None => return false,
+        };
+
+        let map_attributes = self.map.attributes().mods(self.mods);
+        let hit_window = difficulty_range_od(map_attributes.od) / clock_rate;
+        let od = (80.0 - hit_window) / 6.0;
+
+        (attributes.od - od).abs() > 1e-6
+    }
+
+    /// Calculate how much pp each of the given mods adds on its own
+    /// compared to the current score state without mods.
+    ///
+    /// Returns the mods alongside their pp difference.
+    /// Difficulty attributes are only recalculated for mods that affect them.
+    pub fn mod_contributions(&self, mods: &[u32]) -> Vec<(u32, f64)> {
+        let mut nomod = self.clone().mods(0);
+
+        if changes_difficulty(self.mods) {
+            nomod.attributes = None;
+        }
+
+        if nomod.attributes.is_none() {
+            nomod.attributes = Some(nomod.calculate_difficulty());
+        }
+
+        let nomod_pp = nomod.clone().calculate().pp;
+
+        mods.iter()
+            .map(|&m| {
+                let mut calculator = nomod.clone().mods(m);
+
+                if changes_difficulty(m) {
+                    calculator.attributes = None;
                 }
 
-                let mut pp = if self.mods.rx() {
-                    (aim_value.powf(1.17) + acc_value.powf(1.15) + flashlight_value.powf(1.1))
-                        .powf(1.0 / 1.1)
-                        * multiplier
-                } else if self.mods.ap() {
-                    (speed_value.powf(1.12) + acc_value.powf(1.12) + flashlight_value.powf(1.05)).powf(1.0 / 1.1) * multiplier
-                } else {
-                    (aim_value.powf(1.1)
-                        + speed_value.powf(1.1)
-                        + acc_value.powf(1.1)
-                        + flashlight_value.powf(1.1))
-                    .powf(1.0 / 1.1)
-                        * multiplier
-                };
+                (m, calculator.calculate().pp - nomod_pp)
+            })
+            .collect()
+    }
 
-                if self.mods.rx() {
-                    match map_id {
-                        1808605 => {
-                            // Louder than steel
-                            pp *= 0.7;
-                        }
-                        1821147 => {
-                            // Over the top
-                            pp *= 0.6;
-                        }
-                        1849420 => {
-                            // Ascension to heaven (mattay)
-                            pp *= 0.6;
-                        }
-                        _ => {}
-                    }
+    /// Calculate the same play without any mods, e.g. to display how much pp
+    /// the mods added onto a score.
+    ///
+    /// All mods are stripped, including relax and autopilot, and a custom
+    /// clock rate is discarded as well. Hit results and other parameters are kept.
+    /// Difficulty attributes are only recalculated if the mods or clock rate affected them.
+    ///
+    /// See [`OsuPerformanceAttributes::pp_delta`].
+    pub fn calculate_nomod_baseline(mut self) -> OsuPerformanceAttributes {
+        if changes_difficulty(self.mods) || self.clock_rate.is_some() {
+            self.attributes = None;
+        }
+
+        self.clock_rate = None;
+
+        self.mods(0).calculate()
+    }
+
+    /// Calculate the performance at regular checkpoints, e.g. for replay heatmaps.
+    ///
+    /// The `i`-th state is the score state after `(i + 1) * step` objects, except for
+    /// the last checkpoint which may contain fewer objects if the map ends before.
+    /// Once all objects are processed, remaining states are ignored.
+    ///
+    /// The difficulty is calculated gradually just like it is done by
+    /// [`OsuGradualPerformanceAttributes`](crate::osu::OsuGradualPerformanceAttributes)
+    /// when processing `step` objects at a time but all other parameters
+    /// of this calculator are kept.
+    /// If `step` is 0 it will be considered as 1.
+    pub fn checkpoints(
+        self,
+        step: usize,
+        states: impl Iterator<Item = OsuScoreState>,
+    ) -> Vec<OsuPerformanceAttributes> {
+        let mut difficulty = OsuGradualDifficultyAttributes::new(self.map, self.mods);
+        let step = step.max(1);
+
+        states
+            .map_while(|state| {
+                let n = step.min(difficulty.len()).checked_sub(1)?;
+                let attributes = difficulty.nth(n)?;
+
+                let performance = self
+                    .clone()
+                    .attributes(attributes)
+                    .state(state)
+                    .passed_objects(difficulty.idx)
+                    .calculate();
+
+                Some(performance)
+            })
+            .collect()
+    }
+
+    /// Calculate the performance for each of the given miss counts.
+    ///
+    /// Difficulty attributes are calculated only once and all other parameters
+    /// are kept the same. If the hitresults were generated through
+    /// [`accuracy`](OsuPP::accuracy), they are generated anew for each miss count
+    /// so that the accuracy is kept as well.
+    /// Useful for "pp if you had N fewer misses" tables.
+    pub fn miss_sweep(&self, miss_counts: &[usize]) -> Vec<OsuPerformanceAttributes> {
+        let mut base = self.clone();
+
+        if base.attributes.is_none() {
+            base.attributes = Some(base.calculate_difficulty());
+        }
+
+        let requested_acc = base.acc.and(base.requested_acc);
+
+        if requested_acc.is_some() {
+            base.n300 = None;
+            base.n100 = None;
+            base.n50 = None;
+        }
+
+        miss_counts
+            .iter()
+            .map(|&n_misses| {
+                let calculator = base.clone().misses(n_misses);
+
+                match requested_acc {
+                    Some(acc) => calculator.accuracy(acc).calculate(),
+                    None => calculator.calculate(),
                 }
+            })
+            .collect()
+    }
 
-                (aim_value, speed_value, acc_value, flashlight_value, pp)
-            };
+    /// Calculate the performance for each of the given accuracies between `0` and `100`.
+    ///
+    /// Difficulty attributes are calculated only once and all other parameters,
+    /// e.g. misses and combo, are kept the same.
+    /// Useful for pp curves like 95%, 96%, ..., 100% accuracy.
+    pub fn accuracy_curve(mut self, accuracies: &[f64]) -> Vec<OsuPerformanceAttributes> {
+        if self.attributes.is_none() {
+            self.attributes = Some(self.calculate_difficulty());
+        }
+
+        accuracies
+            .iter()
+            .map(|&acc| self.clone().accuracy(acc).calculate())
+            .collect()
+    }
+
+    /// Find the lowest accuracy between `0` and `100` with which a full combo
+    /// without misses reaches the target pp, e.g. for "how good do I need to play" features.
+    ///
+    /// All other parameters, e.g. mods, are kept and the difficulty attributes are calculated
+    /// only once. The accuracy is determined through binary search up to a tolerance of
+    /// `0.01` percentage points, rounding up so that the target is reached. The returned
+    /// value is the accuracy of the hitresults that are generated for the found accuracy.
+    ///
+    /// Returns `None` if not even an SS reaches the target pp.
+    pub fn accuracy_for_pp(mut self, target_pp: f64) -> Option<f64> {
+        const TOLERANCE: f64 = 0.01;
+
+        let attributes = self.take_attributes();
+        self.combo = Some(attributes.max_combo);
+        self.attributes = Some(attributes);
+
+        self.n300 = None;
+        self.n100 = None;
+        self.n50 = None;
+        self = self.misses(0);
+
+        let pp_with = |acc: f64| self.clone().accuracy(acc).calculate().pp;
+
+        if pp_with(100.0) < target_pp {
+            return None;
+        }
+
+        let (mut low, mut high) = (0.0, 100.0);
+
+        while high - low > TOLERANCE {
+            let mid = (low + high) / 2.0;
+
+            if pp_with(mid) >= target_pp {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+
+        // Use the accuracy of the generated hitresults instead of the searched value
+        self.accuracy(high).acc.map(|acc| 100.0 * acc)
+    }
+
+    /// Calculate how much more pp the score is worth with the accounting of lazer
+    /// compared to stable, i.e. lazer pp minus stable pp.
+    ///
+    /// Both modes use the same difficulty attributes which are calculated only once.
+    /// See [`ScoringMode`] for the differences between them.
+    pub fn scoring_mode_delta(mut self) -> f64 {
+        let attributes = self.take_attributes();
+        self.attributes = Some(attributes);
+
+        let stable = self.clone().scoring_mode(ScoringMode::Stable).calculate();
+        let lazer = self.scoring_mode(ScoringMode::Lazer).calculate();
+
+        lazer.pp - stable.pp
+    }
+
+    /// Find the lowest combo with which the score reaches the target pp while
+    /// keeping the hitresults, accuracy, and misses as they are.
+    ///
+    /// A higher combo never decreases the pp because it lowers the combo based
+    /// miss estimation as well as the combo scaling of the flashlight value.
+    /// Hence, the combo is determined exactly through binary search. The difficulty
+    /// attributes are calculated only once.
+    ///
+    /// Returns `None` if not even the map's max combo reaches the target pp.
+    pub fn combo_for_pp(mut self, target_pp: f64) -> Option<usize> {
+        let attributes = self.take_attributes();
+        let max_combo = attributes.max_combo;
+        self.attributes = Some(attributes);
+
+        let pp_with = |combo: usize| self.clone().combo(combo).calculate().pp;
+
+        if pp_with(max_combo) < target_pp {
+            return None;
+        }
+
+        let (mut low, mut high) = (0, max_combo);
+
+        while low < high {
+            let mid = (low + high) / 2;
+
+            if pp_with(mid) >= target_pp {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        Some(high)
+    }
+
+    /// Calculate the performance of two score states on the same map,
+    /// calculating the difficulty attributes only once.
+    pub fn compare_states(
+        &self,
+        a: OsuScoreState,
+        b: OsuScoreState,
+    ) -> (OsuPerformanceAttributes, OsuPerformanceAttributes) {
+        let mut base = self.clone();
+
+        if base.attributes.is_none() {
+            base.attributes = Some(base.calculate_difficulty());
+        }
+
+        let a = base.clone().state(a).calculate();
+        let b = base.state(b).calculate();
+
+        (a, b)
+    }
+
+    /// Guess the amount of misses based on the combo and the map's max combo.
+    fn estimate_misses(&self, combo: usize) -> usize {
+        let (max_combo, n_sliders) = match self.attributes {
+            Some(ref attributes) => (attributes.max_combo, attributes.n_sliders),
+            None => (self.map.max_combo(), self.map.n_sliders as usize),
+        };
+
+        let full_combo_threshold = max_combo as f64 - 0.1 * n_sliders as f64;
+
+        if (combo as f64) < full_combo_threshold {
+            (full_combo_threshold / combo.max(1) as f64).floor() as usize
+        } else {
+            0
+        }
+    }
+
+    fn calculate_difficulty(&self) -> OsuDifficultyAttributes {
+        let mut calculator = OsuStars::new(self.map).mods(self.mods);
+
+        if let Some(passed_objects) = self.passed_objects {
+            calculator = calculator.passed_objects(passed_objects);
+        }
+
+        if let Some(clock_rate) = self.clock_rate {
+            calculator = calculator.clock_rate(clock_rate);
+        }
+
+        calculator.calculate()
+    }
+}
+
+struct OsuPPInner<'a> {
+    attributes: OsuDifficultyAttributes,
+    mods: u32,
+    acc: f64,
+    combo: Option<usize>,
+
+    n300: usize,
+    n100: usize,
+    n50: usize,
+
+    total_hits: f64,
+    effective_misses: f64,
+    slider_ends_dropped: f64,
+    quality_multiplier: f64,
+    global_multiplier: f64,
+    unstable_rate: Option<f64>,
+    acc_circle_bonus: (f64, f64),
+    rx_speed_weight: f64,
+    slider_velocity_bonus: f64,
+    difficult_slider_ratio: f64,
+    rx_acc_profile: RelaxAccProfile,
+    rx_ht_multipliers: (f64, f64),
+    rx_flashlight_combo_exponent: f64,
+    relax_nf_penalty: bool,
+    rx_overrides: &'a HashMap<i32, f64>,
+    state: OsuScoreState,
+}
+
+/// Recalculate the given performance for different mods if they don't affect the difficulty.
+pub(crate) fn recalculate_with_mods(
+    performance: &OsuPerformanceAttributes,
+    new_mods: u32,
+) -> Option<OsuPerformanceAttributes> {
+    if changes_difficulty(performance.mods ^ new_mods) {
+        None
+    } else {
+        Some(recalculate_performance(performance, new_mods))
+    }
+}
+
+/// Rerun the pp formula on the difficulty attributes and score state of the given
+/// performance with the given mods.
+pub(crate) fn recalculate_performance(
+    performance: &OsuPerformanceAttributes,
+    mods: u32,
+) -> OsuPerformanceAttributes {
+    let calculator = recalculation_base(performance)
+        .mods(mods)
+        .state(performance.state.clone());
+
+    recalculate(performance, &calculator)
+}
+
+/// Rerun the pp formula on the difficulty attributes, combo, and misses
+/// of the given performance with hitresults generated for the given accuracy.
+pub(crate) fn recalculate_accuracy(
+    performance: &OsuPerformanceAttributes,
+    acc: f64,
+) -> OsuPerformanceAttributes {
+    let state = &performance.state;
+
+    let calculator = recalculation_base(performance)
+        .combo(state.max_combo)
+        .misses(state.misses)
+        .accuracy(acc);
+
+    recalculate(performance, &calculator)
+}
+
+/// A calculator for the performance's difficulty attributes, mods, and quality multiplier.
+fn recalculation_base(performance: &OsuPerformanceAttributes) -> OsuPP<'static> {
+    OsuPP::from_attributes(performance.difficulty.clone())
+        .mods(performance.mods)
+        .quality_multiplier(performance.quality_multiplier)
+}
+
+fn recalculate(
+    performance: &OsuPerformanceAttributes,
+    calculator: &OsuPP<'_>,
+) -> OsuPerformanceAttributes {
+    let mut inner = calculator.assert_hitresults(performance.difficulty.clone());
+
+    // Slider breaks may have been specified explicitly so keep the previous value
+    inner.effective_misses = performance.effective_misses;
+
+    inner.calculate(&calculator.map.beatmap_id).0
+}
+
+impl OsuPPInner<'_> {
+    fn calculate(self, map_id: &i32) -> (OsuPerformanceAttributes, PpTrace) {
+        let mut trace = PpTrace {
+            effective_misses: self.effective_misses,
+            ..Default::default()
+        };
+
+        let (aim_value, speed_value, acc_value, flashlight_value, pp) =
+            self.compute_values(map_id, &mut trace);
+
+        let (len_bonus, aim_miss_penalty, speed_miss_penalty) = if self.is_empty() {
+            (1.0, 1.0, 1.0)
+        } else {
+            (
+                self.len_bonus(),
+                self.aim_miss_penalty(),
+                self.speed_miss_penalty(),
+            )
+        };
 
-        OsuPerformanceAttributes {
+        let attributes = OsuPerformanceAttributes {
             difficulty: self.attributes,
             pp_acc: acc_value,
             pp_aim: aim_value,
             pp_flashlight: flashlight_value,
             pp_speed: speed_value,
             pp,
+            quality_multiplier: self.quality_multiplier,
+            len_bonus,
+            aim_miss_penalty,
+            speed_miss_penalty,
+            effective_misses: self.effective_misses,
+            state: self.state,
+            mods: self.mods,
+        };
+
+        (attributes, trace)
+    }
+
+    /// Same as [`calculate`](OsuPPInner::calculate) but only the final pp are returned.
+    #[inline]
+    fn calculate_pp(&self, map_id: &i32) -> f64 {
+        self.compute_values(map_id, &mut PpTrace::default()).4
+    }
+
+    // Plays without any hit object don't have a meaningful performance
+    fn is_empty(&self) -> bool {
+        let n_hits = self.n300 + self.n100 + self.n50;
+
+        self.total_hits.abs() <= f64::EPSILON || n_hits == 0
+    }
+
+    /// Compute the aim, speed, accuracy, and flashlight values as well as the final pp.
+    fn compute_values(&self, map_id: &i32, trace: &mut PpTrace) -> (f64, f64, f64, f64, f64) {
+        debug_assert!(
+            self.effective_misses <= self.total_hits,
+            "more effective misses than hits: {} > {}",
+            self.effective_misses,
+            self.total_hits,
+        );
+
+        if self.is_empty() {
+            (0.0, 0.0, 0.0, 0.0, 0.0)
+        } else {
+            let mut multiplier = 1.12;
+
+            // NF penalty
+            trace.nf_multiplier = 1.0;
+
+            if self.mods.nf() && (self.relax_nf_penalty || !self.mods.rx()) {
+                trace.nf_multiplier = (1.0 - 0.02 * self.effective_misses).max(0.9);
+                multiplier *= trace.nf_multiplier;
+            }
+
+            // SO penalty
+            trace.so_multiplier = 1.0;
+
+            // Empty plays are skipped above but guard against dividing by zero regardless
+            if self.mods.so() && self.total_hits > 0.0 {
+                let n_spinners = self.attributes.n_spinners;
+                trace.so_multiplier = 1.0 - math::powf(n_spinners as f64 / self.total_hits, 0.85);
+                multiplier *= trace.so_multiplier;
+            }
+
+            trace.multiplier = multiplier;
+
+            // Autopilot handles the cursor so aim is not rewarded at all
+            let mut aim_value = if self.mods.ap() {
+                0.0
+            } else {
+                self.compute_aim_value(&mut trace.aim)
+            };
+            let speed_value = self.compute_speed_value(&mut trace.speed);
+            let mut acc_value = self.compute_accuracy_value(&mut trace.acc);
+            let flashlight_value = self.compute_flashlight_value(&mut trace.flashlight);
+
+            self.combine_values(
+                &mut aim_value,
+                speed_value,
+                &mut acc_value,
+                flashlight_value,
+                trace,
+            );
+
+            trace.map_multiplier = 1.0;
+
+            if self.mods.rx() {
+                trace.map_multiplier = match self.rx_overrides.get(map_id) {
+                    Some(multiplier) => *multiplier,
+                    None => default_rx_map_multiplier(*map_id),
+                };
+            }
+
+            trace.quality_multiplier = self.quality_multiplier;
+            trace.global_multiplier = self.global_multiplier;
+
+            let pp = finite_or_zero(trace.aggregated * trace.total_multiplier());
+            trace.pp = pp;
+
+            (aim_value, speed_value, acc_value, flashlight_value, pp)
+        }
+    }
+
+    /// Apply the RX adjustments onto the aim and accuracy value and
+    /// aggregate all skill values into [`PpTrace::aggregated`].
+    fn combine_values(
+        &self,
+        aim_value: &mut f64,
+        speed_value: f64,
+        acc_value: &mut f64,
+        flashlight_value: f64,
+        trace: &mut PpTrace,
+    ) {
+        // RX HT adjustment
+        if self.mods.rx() && self.mods.ht() {
+            let (aim_multiplier, acc_multiplier) = self.rx_ht_multipliers;
+            *aim_value *= aim_multiplier;
+            *acc_value *= acc_multiplier;
+        }
+
+        // RX stream penalty
+        trace.rx_depression_factor = 1.0;
+
+        if self.mods.rx() {
+            let stream_factor = *aim_value / speed_value;
+
+            if stream_factor < 1.0 {
+                // Linearly from 0.85 at 95% up to 0.92 at 100%
+                let depression_factor = (0.92 - (1.0 - self.acc) * 1.4).clamp(0.85, 0.92);
+
+                *aim_value *= depression_factor;
+                trace.rx_depression_factor = depression_factor;
+            }
         }
+
+        let (aim_value, acc_value) = (*aim_value, *acc_value);
+
+        trace.aggregated = if self.mods.rx() {
+            math::powf(
+                math::powf(aim_value, 1.17)
+                    + speed_value * self.rx_speed_weight
+                    + math::powf(acc_value, 1.15)
+                    + math::powf(flashlight_value, 1.1),
+                1.0 / 1.1,
+            )
+        } else if self.mods.ap() {
+            math::powf(
+                math::powf(speed_value, 1.12)
+                    + math::powf(acc_value, 1.12)
+                    + math::powf(flashlight_value, 1.05),
+                1.0 / 1.1,
+            )
+        } else {
+            math::powf(
+                math::powf(aim_value, 1.1)
+                    + math::powf(speed_value, 1.1)
+                    + math::powf(acc_value, 1.1)
+                    + math::powf(flashlight_value, 1.1),
+                1.0 / 1.1,
+            )
+        };
     }
 
-    fn compute_aim_value(&self) -> f64 {
+    fn compute_aim_value(&self, trace: &mut SkillTrace) -> f64 {
         let attributes = &self.attributes;
-        let total_hits = self.total_hits;
 
         // TD penalty
         let raw_aim = if self.mods.td() {
-            attributes.aim_strain.powf(0.8)
+            math::powf(attributes.aim_strain, 0.8)
         } else {
             attributes.aim_strain
         };
 
-        let mut aim_value = (5.0 * (raw_aim / 0.0675).max(1.0) - 4.0).powi(3) / 100_000.0;
+        let mut aim_value = math::powi(5.0 * (raw_aim / 0.0675).max(1.0) - 4.0, 3) / 100_000.0;
+        trace.raw = aim_value;
 
         // Longer maps are worth more
-        let len_bonus = 0.95
-            + 0.4 * (total_hits / 2000.0).min(1.0)
-            + (total_hits > 2000.0) as u8 as f64 * 0.5 * (total_hits / 2000.0).log10();
+        let len_bonus = self.len_bonus();
         aim_value *= len_bonus;
 
         // Penalize misses
-        let effective_misses = self.effective_misses as f64;
-        if effective_misses > 0.0 {
-            aim_value *=
-                calculate_miss_penalty(effective_misses, attributes.aim_difficult_strain_count);
-        }
+        aim_value *= self.aim_miss_penalty();
+
+        trace.penalized = aim_value;
 
         // AR bonus
-        let ar_factor = if self.mods.rx() {
-            if attributes.ar > 10.7 {
-                0.4 * (attributes.ar - 10.7)
-            } else {
-                0.0
-            }
-        } else {
-            if attributes.ar > 10.33 {
-                0.3 * (attributes.ar - 10.33)
-            } else {
-                0.0
-            }
-        };
+        let ar_factor = self.high_ar_factor();
 
         if ar_factor > 0.0 {
             aim_value *= 1.0 + ar_factor * len_bonus; // * Buff for longer maps with high AR.
@@ -496,333 +1745,2361 @@ impl OsuPPInner {
         }
 
         // CS bonus
-        if attributes.cs > 6.0 && self.mods.rx() {            
+        if attributes.cs > 6.0 && self.mods.rx() {
             let diff = attributes.cs - 6.0;
-            aim_value *= 1.03 + (diff / 20.0);  
+            aim_value *= 1.03 + (diff / 20.0);
         }
 
-        // HD bonus (this would include the Blinds mod but it's currently not representable)
+        // Blinds or HD bonus
         let hd_factor = match self.mods.rx() {
             true => (0.05, 11.0),
             _ => (0.04, 12.0),
         };
 
-        if self.mods.hd() {
-            aim_value *= 1.0 + hd_factor.0 * (hd_factor.1 - attributes.ar);
-        }
+        // The Blinds bonus is part of the accuracy factor
+        if self.mods.hd() && !self.mods.bl() {
+            aim_value *= 1.0 + hd_factor.0 * (hd_factor.1 - attributes.ar);
+        }
+
+        // High slider velocity bonus
+        let high_velocity = (attributes.avg_slider_velocity - 1.0).max(0.0);
+        aim_value *= 1.0 + self.slider_velocity_bonus * high_velocity;
+
+        aim_value *= 0.98 + attributes.od * attributes.od / 2500.0;
+
+        aim_value *= self.aim_accuracy_factor();
+        aim_value = finite_or_zero(aim_value);
+        trace.scaled = aim_value;
+
+        aim_value
+    }
+
+    /// The factors of the aim value that depend on the accuracy or hitresults,
+    /// i.e. the Blinds bonus, the slider nerf, and the accuracy itself.
+    fn aim_accuracy_factor(&self) -> f64 {
+        let attributes = &self.attributes;
+        let mut factor = self.acc;
+
+        if self.mods.bl() {
+            factor *= 1.3
+                + (self.total_hits
+                    * (0.0016 / (1.0 + 2.0 * self.effective_misses))
+                    * math::powi(self.acc, 16))
+                    * (1.0 - 0.003 * attributes.hp * attributes.hp);
+        }
+
+        if attributes.n_sliders > 0 && self.difficult_slider_ratio > 0.0 {
+            // * By default we assume 15% of sliders in a map are difficult since
+            // * there's no way to tell from the performance calculator.
+            let estimate_difficult_sliders =
+                attributes.n_sliders as f64 * self.difficult_slider_ratio;
+
+            let estimate_slider_ends_dropped = self
+                .slider_ends_dropped
+                .clamp(0.0, estimate_difficult_sliders);
+
+            let base = 1.0 - estimate_slider_ends_dropped / estimate_difficult_sliders;
+            let slider_nerf_factor =
+                (1.0 - attributes.slider_factor) * base * base * base + attributes.slider_factor;
+
+            factor *= slider_nerf_factor;
+        }
+
+        factor
+    }
+
+    /// Bonus factor for longer maps, shared by aim and speed.
+    fn len_bonus(&self) -> f64 {
+        let total_hits = self.total_hits;
+
+        0.95 + 0.4 * (total_hits / 2000.0).min(1.0)
+            + (total_hits > 2000.0) as u8 as f64 * 0.5 * math::log10(total_hits / 2000.0)
+    }
+
+    fn aim_miss_penalty(&self) -> f64 {
+        if self.effective_misses > 0.0 {
+            calculate_miss_penalty(
+                self.effective_misses,
+                self.attributes.aim_difficult_strain_count,
+            )
+        } else {
+            1.0
+        }
+    }
+
+    fn speed_miss_penalty(&self) -> f64 {
+        if self.effective_misses > 0.0 {
+            calculate_miss_penalty(
+                self.effective_misses,
+                self.attributes.speed_difficult_strain_count,
+            )
+        } else {
+            1.0
+        }
+    }
+
+    /// Bonus factor for high AR, shared by aim and speed.
+    fn high_ar_factor(&self) -> f64 {
+        let ar = self.attributes.ar;
+
+        let (threshold, scale) = if self.mods.rx() {
+            (10.7, 0.4)
+        } else {
+            (10.33, 0.3)
+        };
+
+        if ar > threshold {
+            scale * (ar - threshold)
+        } else {
+            0.0
+        }
+    }
+
+    fn compute_speed_value(&self, trace: &mut SkillTrace) -> f64 {
+        let attributes = &self.attributes;
+
+        let mut speed_value =
+            math::powi(5.0 * (attributes.speed_strain / 0.0675).max(1.0) - 4.0, 3) / 100_000.0;
+        trace.raw = speed_value;
+
+        // Longer maps are worth more
+        let len_bonus = self.len_bonus();
+        speed_value *= len_bonus;
+
+        // Penalize misses
+        speed_value *= self.speed_miss_penalty();
+
+        trace.penalized = speed_value;
+
+        // AR bonus
+        let ar_factor = self.high_ar_factor();
+
+        if ar_factor > 0.0 {
+            speed_value *= 1.0 + ar_factor * len_bonus; // * Buff for longer maps with high AR.
+        }
+
+        // Blinds or HD bonus
+        let hd_factor = match self.mods.rx() {
+            true => (0.05, 11.0),
+            _ => (0.04, 12.0),
+        };
+
+        if self.mods.bl() {
+            // * Increasing the speed value by object count for Blinds isn't
+            // * ideal, so the minimum buff is given.
+            speed_value *= 1.12;
+        } else if self.mods.hd() {
+            speed_value *= 1.0 + hd_factor.0 * (hd_factor.1 - attributes.ar);
+        }
+
+        // Scaling the speed value with OD
+        speed_value *= 0.95 + attributes.od * attributes.od / 750.0;
+
+        speed_value *= self.speed_accuracy_factor();
+        speed_value = finite_or_zero(speed_value);
+        trace.scaled = speed_value;
+
+        speed_value
+    }
+
+    /// The factors of the speed value that depend on the accuracy or hitresults,
+    /// i.e. the accuracy scaling and the 50s penalty.
+    fn speed_accuracy_factor(&self) -> f64 {
+        let attributes = &self.attributes;
+        let total_hits = self.total_hits;
+
+        let acc = match self.unstable_rate {
+            Some(unstable_rate) => {
+                math::sqrt(self.acc * estimate_great_ratio(unstable_rate, attributes.od))
+            }
+            None => self.acc,
+        };
+
+        let acc_factor = math::powf(acc, (14.5 - attributes.od.max(8.0)) / 2.0);
+
+        // Penalize n50s
+        let n50_factor: f64 = if self.mods.ap() { 0.96 } else { 0.98 };
+
+        acc_factor
+            * math::powf(
+                n50_factor,
+                (self.n50 as f64 >= total_hits / 500.0) as u8 as f64
+                    * (self.n50 as f64 - total_hits / 500.0),
+            )
+    }
+
+    fn compute_accuracy_value(&self, trace: &mut SkillTrace) -> f64 {
+        let attributes = &self.attributes;
+        let total_hits = self.total_hits;
+        let n_circles = attributes.n_circles as f64;
+        let n300 = self.n300 as f64;
+        let n100 = self.n100 as f64;
+        let n50 = self.n50 as f64;
+
+        let better_acc_percentage = (n_circles > 0.0) as u8 as f64
+            * (((n300 - (total_hits - n_circles)) * 6.0 + n100 * 2.0 + n50) / (n_circles * 6.0))
+                .max(0.0);
+
+        let mut acc_value =
+            math::powf(1.52163_f64, attributes.od) * math::powi(better_acc_percentage, 24) * 2.83;
+        trace.raw = acc_value;
+        trace.penalized = acc_value;
+
+        // Bonus for many hitcircles
+        let (exponent, cap) = self.acc_circle_bonus;
+        acc_value *= math::powf(n_circles / 1000.0, exponent).min(cap);
+
+        // Blinds or HD bonus
+        if self.mods.bl() {
+            acc_value *= 1.14;
+        } else if self.mods.hd() {
+            acc_value *= 1.08;
+        }
+
+        // FL bonus
+        if self.mods.fl() {
+            acc_value *= 1.02;
+        }
+
+        // RX adjustment
+        if self.mods.rx() {
+            acc_value = self.rx_acc_profile.apply(acc_value);
+        }
+
+        acc_value = finite_or_zero(acc_value);
+        trace.scaled = acc_value;
+
+        acc_value
+    }
+
+    fn compute_flashlight_value(&self, trace: &mut SkillTrace) -> f64 {
+        if !self.mods.fl() {
+            return 0.0;
+        }
+
+        let attributes = &self.attributes;
+        let total_hits = self.total_hits;
+
+        // TD penalty
+        let raw_flashlight = if self.mods.td() {
+            math::powf(attributes.flashlight_rating, 0.8)
+        } else {
+            attributes.flashlight_rating
+        };
+
+        let mut flashlight_value = raw_flashlight * raw_flashlight * 25.0;
+        trace.raw = flashlight_value;
+
+        // Add an additional bonus for HDFL
+        if self.mods.hd() {
+            flashlight_value *= 1.3;
+        }
+
+        // Penalize misses by assessing # of misses relative to the total # of objects.
+        // Default a 3% reduction for any # of misses
+        let effective_misses = self.effective_misses;
+        if effective_misses > 0.0 {
+            flashlight_value *= 0.97
+                * math::powf(
+                    1.0 - math::powf(effective_misses / total_hits, 0.775),
+                    math::powf(effective_misses, 0.875),
+                );
+        }
+
+        trace.penalized = flashlight_value;
+
+        // Combo scaling
+        if let Some(combo) = self.combo.filter(|_| attributes.max_combo > 0) {
+            let exponent = if self.mods.rx() {
+                self.rx_flashlight_combo_exponent
+            } else {
+                0.8
+            };
+
+            flashlight_value *=
+                math::powf(combo as f64 / attributes.max_combo as f64, exponent).min(1.0);
+        }
+
+        // Account for shorter maps having a higher ratio of 0 combo/100 combo flashlight radius
+        flashlight_value *= 0.7
+            + 0.1 * (total_hits / 200.0).min(1.0)
+            + (total_hits > 200.0) as u8 as f64 * (0.2 * ((total_hits - 200.0) / 200.0).min(1.0));
+
+        // It is important to also consider accuracy difficulty when scaling with accuracy
+        flashlight_value *= 0.98 + attributes.od * attributes.od / 2500.0;
+
+        flashlight_value *= self.flashlight_accuracy_factor();
+        flashlight_value = finite_or_zero(flashlight_value);
+        trace.scaled = flashlight_value;
+
+        flashlight_value
+    }
+
+    /// Scale the flashlight value with accuracy _slightly_.
+    #[inline]
+    fn flashlight_accuracy_factor(&self) -> f64 {
+        0.5 + self.acc / 2.0
+    }
+}
+
+/// Probability that a normally distributed hit error lies within `[-window, window]`.
+fn hit_probability(window: f64, mean: f64, deviation: f64) -> f64 {
+    if deviation <= f64::EPSILON {
+        return (mean.abs() <= window) as u8 as f64;
+    }
+
+    let scale = std::f64::consts::SQRT_2 * deviation;
+
+    0.5 * (erf((window - mean) / scale) + erf((window + mean) / scale))
+}
+
+fn estimate_great_ratio(unstable_rate: f64, od: f64) -> f64 {
+    // The unstable rate is ten times the standard deviation of hit errors
+    let deviation = unstable_rate / 10.0;
+    let great_hit_window = 80.0 - 6.0 * od;
+
+    if deviation <= f64::EPSILON {
+        return 1.0;
+    }
+
+    erf(great_hit_window / (std::f64::consts::SQRT_2 * deviation))
+}
+
+// Abramowitz and Stegun approximation, maximum error of 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = x.signum();
+    let x = x.abs();
+
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+
+    sign * (1.0 - poly * math::exp(-x * x))
+}
+
+fn default_rx_map_multiplier(map_id: i32) -> f64 {
+    match map_id {
+        // Louder than steel
+        1808605 => 0.7,
+        // Over the top
+        1821147 => 0.6,
+        // Ascension to heaven (mattay)
+        1849420 => 0.6,
+        _ => 1.0,
+    }
+}
+
+#[inline]
+fn changes_difficulty(mods: u32) -> bool {
+    mods.change_map() || mods.fl()
+}
+
+fn calculate_miss_penalty(n_misses: f64, difficult_strain_count: f64) -> f64 {
+    // Miss penalty assumes that a player will miss on the hardest parts of a map,
+    // so we use the amount of relatively difficult sections to adjust miss penalty
+    // to make it more punishing on maps with lower amount of hard sections.
+    0.94 / ((n_misses / (2.0 * math::sqrt(difficult_strain_count))) + 1.0)
+}
+
+/// Replace NaN and infinity with zero so that degenerate
+/// attributes don't propagate into the final pp.
+#[inline]
+fn finite_or_zero(value: f64) -> f64 {
+    if value.is_finite() {
+        value
+    } else {
+        0.0
+    }
+}
+
+fn calculate_effective_misses(
+    attributes: &OsuDifficultyAttributes,
+    combo: Option<usize>,
+    n_misses: usize,
+    total_hits: f64,
+) -> f64 {
+    // * Guess the number of misses + slider breaks from combo
+    let mut combo_based_misses: f64 = 0.0;
+
+    if attributes.n_sliders > 0 {
+        let full_combo_threshold = attributes.max_combo as f64 - 0.1 * attributes.n_sliders as f64;
+
+        let f64_combo = combo.map(|c| c as f64);
+
+        if let Some(combo) = f64_combo.filter(|&c| c < full_combo_threshold) {
+            combo_based_misses = full_combo_threshold / combo.max(1.0);
+        }
+    }
+
+    // * Clamp misscount since it's derived from combo and can be
+    // * higher than total hits and that breaks some calculations
+    combo_based_misses = combo_based_misses.min(total_hits);
+
+    combo_based_misses.max(n_misses as f64)
+}
+
+/// Abstract type to provide flexibility when passing difficulty attributes to a performance calculation.
+pub trait OsuAttributeProvider {
+    /// Provide the actual difficulty attributes.
+    fn attributes(self) -> Option<OsuDifficultyAttributes>;
+}
+
+impl OsuAttributeProvider for OsuDifficultyAttributes {
+    #[inline]
+    fn attributes(self) -> Option<OsuDifficultyAttributes> {
+        Some(self)
+    }
+}
+
+impl OsuAttributeProvider for OsuPerformanceAttributes {
+    #[inline]
+    fn attributes(self) -> Option<OsuDifficultyAttributes> {
+        Some(self.difficulty)
+    }
+}
+
+impl OsuAttributeProvider for DifficultyAttributes {
+    #[inline]
+    fn attributes(self) -> Option<OsuDifficultyAttributes> {
+        #[allow(irrefutable_let_patterns)]
+        if let Self::Osu(attributes) = self {
+            Some(attributes)
+        } else {
+            None
+        }
+    }
+}
+
+impl OsuAttributeProvider for PerformanceAttributes {
+    #[inline]
+    fn attributes(self) -> Option<OsuDifficultyAttributes> {
+        #[allow(irrefutable_let_patterns)]
+        if let Self::Osu(attributes) = self {
+            Some(attributes.difficulty)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Beatmap;
+
+    #[test]
+    fn osu_only_accuracy() {
+        let map = Beatmap::default();
+
+        let total_objects = 1234;
+        let target_acc = 97.5;
+
+        let calculator = OsuPP::new(&map)
+            .passed_objects(total_objects)
+            .accuracy(target_acc);
+
+        let numerator = 6 * calculator.n300.unwrap_or(0)
+            + 2 * calculator.n100.unwrap_or(0)
+            + calculator.n50.unwrap_or(0);
+        let denominator = 6 * total_objects;
+        let acc = 100.0 * numerator as f64 / denominator as f64;
+
+        assert!(
+            (target_acc - acc).abs() < 1.0,
+            "Expected: {} | Actual: {}",
+            target_acc,
+            acc
+        );
+    }
+
+    #[test]
+    fn osu_accuracy_and_n50() {
+        let map = Beatmap::default();
+
+        let total_objects = 1234;
+        let target_acc = 97.5;
+        let n50 = 30;
+
+        let calculator = OsuPP::new(&map)
+            .passed_objects(total_objects)
+            .n50(n50)
+            .accuracy(target_acc);
+
+        assert!(
+            (calculator.n50.unwrap() as i32 - n50 as i32).abs() <= 4,
+            "Expected: {} | Actual: {}",
+            n50,
+            calculator.n50.unwrap()
+        );
+
+        let numerator = 6 * calculator.n300.unwrap_or(0)
+            + 2 * calculator.n100.unwrap_or(0)
+            + calculator.n50.unwrap_or(0);
+        let denominator = 6 * total_objects;
+        let acc = 100.0 * numerator as f64 / denominator as f64;
+
+        assert!(
+            (target_acc - acc).abs() < 1.0,
+            "Expected: {} | Actual: {}",
+            target_acc,
+            acc
+        );
+    }
+
+    #[test]
+    fn osu_accuracy_and_n300() {
+        let map = Beatmap::default();
+
+        let total_objects = 1234;
+        let target_acc = 91.0;
+        let n300 = 1100;
+
+        let calculator = OsuPP::new(&map)
+            .passed_objects(total_objects)
+            .n300(n300)
+            .accuracy(target_acc);
+
+        assert_eq!(n300, calculator.n300.unwrap());
+
+        let numerator = 6 * calculator.n300.unwrap_or(0)
+            + 2 * calculator.n100.unwrap_or(0)
+            + calculator.n50.unwrap_or(0);
+        let denominator = 6 * total_objects;
+        let acc = 100.0 * numerator as f64 / denominator as f64;
+
+        assert!(
+            (target_acc - acc).abs() < 0.1,
+            "Expected: {} | Actual: {}",
+            target_acc,
+            acc
+        );
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_autoplay_ignored() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let mods = 8 + 64; // HDDT
+        let autoplay = mods | <u32 as Mods>::AT;
+
+        assert!(autoplay.is_unranked());
+        assert!(!mods.is_unranked());
+
+        let regular = OsuPP::new(&map).mods(mods).calculate();
+        let auto = OsuPP::new(&map).mods(autoplay).calculate();
+
+        assert_eq!(regular.pp, auto.pp);
+        assert_eq!(regular.difficulty, auto.difficulty);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_mod_contributions() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let calculator = OsuPP::new(&map).misses(1).accuracy(98.0);
+
+        let hd = <u32 as Mods>::HD;
+        let hr = <u32 as Mods>::HR;
+        let contributions = calculator.mod_contributions(&[hd, hr]);
+
+        assert_eq!(contributions.len(), 2);
+        assert_eq!(contributions[0].0, hd);
+        assert_eq!(contributions[1].0, hr);
+        assert!(contributions[0].1 > 0.0);
+        assert!(contributions[1].1 > 0.0);
+
+        let nomod = calculator.clone().calculate().pp;
+        let with_hr = calculator.mods(hr).calculate().pp;
+
+        assert!((with_hr - nomod - contributions[1].1).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn osu_hitresult_after_accuracy() {
+        let map = Beatmap::default();
+        let attributes = OsuDifficultyAttributes::default();
+
+        let total_objects = 1234;
+        let n50 = 50;
+
+        let calculator = OsuPP::new(&map)
+            .passed_objects(total_objects)
+            .accuracy(99.0);
+
+        let stale_acc = calculator.acc.unwrap();
+        let n300 = calculator.n300.unwrap();
+
+        // Turn some 300s into 50s
+        let calculator = calculator.n300(n300 - n50).n50(n50);
+
+        assert!(calculator.acc.is_none());
+
+        let inner = calculator.assert_hitresults(attributes);
+        let numerator = 6 * inner.n300 + 2 * inner.n100 + inner.n50;
+        let acc = numerator as f64 / (6 * total_objects) as f64;
+
+        assert_eq!(inner.n50, n50);
+        assert!((inner.acc - acc).abs() < f64::EPSILON);
+        assert!((inner.acc - stale_acc).abs() > f64::EPSILON);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_quality_multiplier() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let regular = OsuPP::new(&map).accuracy(98.0).calculate();
+
+        let halved = OsuPP::new(&map)
+            .attributes(regular.clone())
+            .quality_multiplier(0.5)
+            .accuracy(98.0)
+            .calculate();
+
+        assert!((halved.pp - regular.pp * 0.5).abs() < 1e-9);
+        assert_eq!(halved.pp_aim, regular.pp_aim);
+        assert_eq!(halved.pp_speed, regular.pp_speed);
+        assert_eq!(halved.pp_acc, regular.pp_acc);
+        assert_eq!(halved.quality_multiplier, 0.5);
+    }
+
+    #[test]
+    fn osu_spun_out_empty_map() {
+        let map = Beatmap::default();
+
+        let (result, trace) = OsuPP::new(&map).mods(4096).calculate_with_trace();
+
+        assert_eq!(result.pp, 0.0);
+        assert!(trace.pp.is_finite());
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_nomod_baseline() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).mods(8 + 64).misses(1).accuracy(98.0);
+        let hddt = calculator.clone().calculate();
+        let baseline = calculator.calculate_nomod_baseline();
+
+        let expected = OsuPP::new(&map).state(hddt.state.clone()).calculate();
+
+        assert_eq!(baseline, expected);
+        assert_eq!(hddt.pp_delta(&baseline), hddt.pp - expected.pp);
+        assert!(hddt.pp_delta(&baseline) > 0.0);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_speed_miss_penalty() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let result = OsuPP::new(&map).misses(3).calculate();
+        let difficulty = &result.difficulty;
+
+        assert!(
+            (difficulty.aim_difficult_strain_count - difficulty.speed_difficult_strain_count).abs()
+                > 1.0
+        );
+
+        assert_eq!(
+            result.aim_miss_penalty,
+            calculate_miss_penalty(3.0, difficulty.aim_difficult_strain_count)
+        );
+        assert_eq!(
+            result.speed_miss_penalty,
+            calculate_miss_penalty(3.0, difficulty.speed_difficult_strain_count)
+        );
+        assert_ne!(result.aim_miss_penalty, result.speed_miss_penalty);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_effective_misses() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        // Choke at a third of the max combo with a single miss
+        let (choke, trace) = OsuPP::new(&map).combo(300).misses(1).calculate_with_trace();
+
+        assert!(choke.effective_misses > 1.0);
+        assert!((choke.effective_misses - trace.effective_misses).abs() < f64::EPSILON);
+
+        let fc = OsuPP::new(&map).calculate();
+        assert_eq!(fc.effective_misses, 0.0);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_max_pp() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        for &mods in &[0, 8 + 64, 128, 8192] {
+            let max_pp = OsuPP::max_pp(&map, mods);
+            let attributes = max_pp.difficulty.clone();
+
+            assert_eq!(max_pp, OsuPP::new(&map).mods(mods).calculate());
+
+            for &(combo, n100, misses) in &[(909, 0, 0), (909, 10, 0), (500, 0, 1), (100, 30, 5)] {
+                let pp = OsuPP::new(&map)
+                    .mods(mods)
+                    .attributes(attributes.clone())
+                    .combo(combo)
+                    .n100(n100)
+                    .misses(misses)
+                    .calculate()
+                    .pp;
+
+                assert!(max_pp.pp >= pp, "mods={}: {} < {}", mods, max_pp.pp, pp);
+            }
+        }
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_checkpoints() {
+        use crate::osu::OsuGradualPerformanceAttributes;
+
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let n_objects = map.hit_objects.len();
+
+        let state = OsuScoreState {
+            max_combo: 909,
+            n300: n_objects,
+            ..Default::default()
+        };
+
+        let single = OsuPP::new(&map)
+            .mods(8)
+            .checkpoints(n_objects, std::iter::repeat(state.clone()));
+
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0], OsuPP::new(&map).mods(8).state(state).calculate());
+
+        let states = (1..=10).map(|i| OsuScoreState {
+            max_combo: 100 * i,
+            n300: (100 * i).min(n_objects),
+            ..Default::default()
+        });
+
+        let checkpoints = OsuPP::new(&map).mods(8).checkpoints(100, states.clone());
+        assert_eq!(checkpoints.len(), 7);
+
+        let mut gradual = OsuGradualPerformanceAttributes::new(&map, 8);
+
+        for (checkpoint, state) in checkpoints.iter().zip(states) {
+            let expected = gradual.process_next_n_objects(state, 100);
+            assert_eq!(Some(checkpoint), expected.as_ref());
+        }
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_global_multiplier() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let regular = OsuPP::new(&map)
+            .mods(128)
+            .misses(2)
+            .accuracy(97.0)
+            .calculate();
+
+        let (scaled, trace) = OsuPP::new(&map)
+            .attributes(regular.clone())
+            .mods(128)
+            .misses(2)
+            .accuracy(97.0)
+            .global_multiplier(1.1)
+            .calculate_with_trace();
+
+        assert!((scaled.pp - regular.pp * 1.1).abs() < 1e-9);
+        assert_eq!(scaled.pp_aim, regular.pp_aim);
+        assert_eq!(trace.global_multiplier, 1.1);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_unstable_rate() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).calculate();
+
+        let calculate = |unstable_rate: f64| {
+            OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .unstable_rate(unstable_rate)
+                .accuracy(98.0)
+                .calculate()
+        };
+
+        let tight = calculate(80.0);
+        let loose = calculate(120.0);
+
+        assert!(tight.pp_speed > loose.pp_speed);
+        assert_eq!(tight.pp_aim, loose.pp_aim);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_recompute_visibility_mods() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).mods(64).misses(1).accuracy(98.0);
+        let nohd = calculator.clone().calculate();
+
+        let recomputed = nohd.recompute_visibility_mods(64 + 8).unwrap();
+        let fresh = calculator.mods(64 + 8).calculate();
+
+        assert_eq!(recomputed, fresh);
+        assert_eq!(recomputed.difficulty, nohd.difficulty);
+        assert!(recomputed.pp > nohd.pp);
+
+        // Without the map, the difficulty can't be recalculated for other clock rates
+        assert!(nohd.recompute_visibility_mods(8).is_none());
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_recalculate_accuracy() {
+        use super::super::STARS_CALCULATIONS;
+
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).mods(8 + 64).combo(700).misses(2);
+        let performance = calculator.clone().accuracy(99.0).calculate();
+
+        STARS_CALCULATIONS.with(|count| count.set(0));
+        let recalculated = performance.recalculate_accuracy(95.0);
+        assert_eq!(STARS_CALCULATIONS.with(|count| count.get()), 0);
+
+        let fresh = calculator.accuracy(95.0).calculate();
+
+        assert_eq!(recalculated, fresh);
+        assert!(recalculated.pp < performance.pp);
+        assert_eq!(recalculated.state.max_combo, 700);
+        assert_eq!(recalculated.state.misses, 2);
+
+        // Accuracy independent terms are taken from the attributes as they are
+        assert_eq!(recalculated.len_bonus, performance.len_bonus);
+        assert_eq!(recalculated.aim_miss_penalty, performance.aim_miss_penalty);
+        assert_eq!(recalculated.effective_misses, performance.effective_misses);
+
+        // Deserialized attributes contain everything that is required
+        let deserialized = OsuPerformanceAttributes {
+            difficulty: performance.difficulty.clone(),
+            state: performance.state.clone(),
+            mods: performance.mods,
+            effective_misses: performance.effective_misses,
+            quality_multiplier: performance.quality_multiplier,
+            ..Default::default()
+        };
+
+        assert_eq!(deserialized.recalculate_accuracy(95.0), fresh);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_recalculate_accuracy_options() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+
+        let calculators = [
+            OsuPP::new(&map).mods(128).combo(500).misses(3),
+            OsuPP::new(&map).mods(128 + 256),
+            OsuPP::new(&map).mods(8192).misses(1),
+            OsuPP::new(&map).mods(8 + 1024).combo(800),
+            OsuPP::new(&map).mods(1).slider_breaks(2),
+            OsuPP::new(&map).combo(600).quality_multiplier(0.9),
+        ];
+
+        for calculator in calculators.iter() {
+            let calculator = calculator.clone().attributes(attributes.clone());
+            let performance = calculator.clone().accuracy(99.5).calculate();
+
+            for &acc in [100.0, 97.0, 90.0].iter() {
+                let recalculated = performance.recalculate_accuracy(acc);
+                let fresh = calculator.clone().accuracy(acc).calculate();
+
+                assert_eq!(recalculated, fresh, "mods {} acc {}", calculator.mods, acc);
+            }
+        }
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_invalid_difficult_strain_count() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let mut attributes = OsuPP::new(&map).calculate().difficulty;
+        attributes.aim_difficult_strain_count = f64::NAN;
+        attributes.speed_difficult_strain_count = -5.0;
+
+        let result = OsuPP::new(&map)
+            .attributes(attributes)
+            .misses(2)
+            .accuracy(97.0)
+            .calculate();
+
+        assert!(result.pp.is_finite());
+        assert_eq!(result.difficulty.aim_difficult_strain_count, 0.0);
+        assert_eq!(result.difficulty.speed_difficult_strain_count, 0.0);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_miss_sweep() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).mods(8).combo(500);
+        let miss_counts = [0, 1, 5];
+
+        let sweep = calculator.miss_sweep(&miss_counts);
+
+        assert_eq!(sweep.len(), miss_counts.len());
+
+        for (result, &n_misses) in sweep.iter().zip(miss_counts.iter()) {
+            let expected = calculator.clone().misses(n_misses).calculate();
+            assert_eq!(result, &expected);
+        }
+
+        assert!(sweep[0].pp > sweep[2].pp);
+
+        // The hitresults of a given accuracy are generated anew for each miss count
+        let sweep = calculator.clone().accuracy(97.0).miss_sweep(&miss_counts);
+
+        for (result, &n_misses) in sweep.iter().zip(miss_counts.iter()) {
+            let expected = calculator
+                .clone()
+                .misses(n_misses)
+                .accuracy(97.0)
+                .calculate();
+            assert_eq!(result, &expected);
+
+            let state = &result.state;
+            let n_hits = state.n300 + state.n100 + state.n50 + state.misses;
+            assert_eq!(n_hits, map.hit_objects.len());
+            assert_eq!(state.misses, n_misses);
+        }
+
+        assert!(sweep[0].pp > sweep[1].pp && sweep[1].pp > sweep[2].pp);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_autopilot_components() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let result = OsuPP::new(&map)
+            .mods(8 + 1024 + 8192) // HDFLAP
+            .accuracy(98.0)
+            .calculate();
+
+        assert_eq!(result.pp_aim, 0.0);
+        assert!(result.pp_speed > 0.0);
+        assert!(result.pp_acc > 0.0);
+        assert!(result.pp_flashlight > 0.0);
+
+        let aggregated = (result.pp_speed.powf(1.12)
+            + result.pp_acc.powf(1.12)
+            + result.pp_flashlight.powf(1.05))
+        .powf(1.0 / 1.1);
+
+        assert!((result.pp - aggregated * 1.12).abs() < 1e-9);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_accuracy_curve() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).mods(8).misses(1);
+        let accuracies = [95.0, 96.0, 97.0, 98.0, 99.0, 100.0];
+
+        let curve = calculator.clone().accuracy_curve(&accuracies);
+
+        assert_eq!(curve.len(), accuracies.len());
+
+        for (result, &acc) in curve.iter().zip(accuracies.iter()) {
+            let expected = calculator.clone().accuracy(acc).calculate();
+            assert_eq!(result, &expected);
+        }
+
+        assert!(curve.windows(2).all(|pair| pair[0].pp < pair[1].pp));
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_nan_stars_recalculated() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let expected = OsuPP::new(&map).accuracy(98.0).calculate();
+
+        let mut attributes = expected.difficulty.clone();
+        attributes.stars = f64::NAN;
+
+        let result = OsuPP::new(&map)
+            .attributes(attributes)
+            .accuracy(98.0)
+            .calculate();
+
+        assert!(result.stars().is_finite());
+        assert_eq!(result, expected);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_passed_until_ms() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let start_time = map.hit_objects[99].start_time;
+
+        let calculator = OsuPP::new(&map).passed_until_ms(start_time);
+        assert_eq!(calculator.passed_objects, Some(100));
+
+        let calculator = OsuPP::new(&map).mods(64).passed_until_ms(start_time / 1.5);
+        assert_eq!(calculator.passed_objects, Some(100));
+
+        let calculator = OsuPP::new(&map).passed_until_ms(start_time - 1.0);
+        assert_eq!(calculator.passed_objects, Some(99));
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_compare_states() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map);
+        let max_combo = calculator.clone().calculate().max_combo();
+        let n_objects = map.hit_objects.len();
+
+        let a = OsuScoreState {
+            max_combo,
+            n300: n_objects - 5,
+            n100: 5,
+            n50: 0,
+            misses: 0,
+        };
+
+        let b = OsuScoreState {
+            max_combo,
+            n300: n_objects - 30,
+            n100: 25,
+            n50: 5,
+            misses: 0,
+        };
+
+        let (a_attrs, b_attrs) = calculator.compare_states(a, b);
+
+        assert!(a_attrs.pp > b_attrs.pp);
+        assert_eq!(a_attrs.difficulty, b_attrs.difficulty);
+    }
+
+    #[test]
+    fn osu_acc_circle_bonus() {
+        let map = Beatmap::default();
+
+        let attributes = OsuDifficultyAttributes {
+            od: 9.0,
+            n_circles: 2000,
+            n_sliders: 100,
+            stars: 5.0,
+            max_combo: 2300,
+            ..Default::default()
+        };
+
+        let calculate = |cap: f64| {
+            OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .acc_circle_bonus(0.3, cap)
+                .passed_objects(2100)
+                .accuracy(99.0)
+                .calculate()
+                .pp_acc
+        };
+
+        let default = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .passed_objects(2100)
+            .accuracy(99.0)
+            .calculate()
+            .pp_acc;
+
+        assert_eq!(calculate(1.15), default);
+        assert!(calculate(1.3) > default);
+    }
+
+    #[test]
+    fn osu_speed_ar_factor() {
+        let map = Beatmap::default();
+
+        let calculate = |ar: f64, mods: u32| {
+            let attributes = OsuDifficultyAttributes {
+                ar,
+                od: 9.0,
+                aim_strain: 3.0,
+                speed_strain: 3.0,
+                n_circles: 800,
+                n_sliders: 200,
+                stars: 6.0,
+                max_combo: 1400,
+                ..Default::default()
+            };
+
+            OsuPP::new(&map)
+                .attributes(attributes)
+                .mods(mods)
+                .passed_objects(1000)
+                .accuracy(98.0)
+                .calculate()
+                .pp_speed
+        };
+
+        for &mods in &[0, 128] {
+            let ar8 = calculate(8.0, mods);
+            let ar10_5 = calculate(10.5, mods);
+            let ar11 = calculate(11.0, mods);
+
+            assert!(ar8.is_finite() && ar8 > 0.0);
+            assert!(ar8 <= ar10_5);
+            assert!(ar10_5 < ar11);
+        }
+
+        // The RX threshold is higher so AR 10.5 receives no bonus
+        assert_eq!(calculate(8.0, 128), calculate(10.5, 128));
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_all_misses() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        for &mods in &[0, 128, 8192] {
+            let result = OsuPP::new(&map)
+                .mods(mods)
+                .misses(map.hit_objects.len())
+                .calculate();
+
+            assert_eq!(result.pp, 0.0);
+            assert_eq!(result.pp_aim, 0.0);
+            assert_eq!(result.pp_speed, 0.0);
+            assert_eq!(result.pp_acc, 0.0);
+            assert!(result.stars() > 0.0);
+        }
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_effective_misses_smooth() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+        let max_combo = attributes.max_combo;
+
+        let pps: Vec<_> = (max_combo / 4..max_combo / 4 + 20)
+            .map(|combo| {
+                OsuPP::new(&map)
+                    .attributes(attributes.clone())
+                    .combo(combo)
+                    .n100(5)
+                    .calculate()
+                    .pp
+            })
+            .collect();
+
+        for window in pps.windows(2) {
+            let diff = window[1] - window[0];
+            assert!(diff > 0.0);
+            assert!(diff < 1.0);
+        }
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_report() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map)
+            .mods(8 + 64)
+            .combo(500)
+            .misses(2)
+            .accuracy(97.5);
+
+        let report = calculator.clone().report();
+        let result = calculator.calculate();
+
+        assert_eq!(report.pp, result.pp());
+        assert_eq!(report.stars, result.stars());
+        assert_eq!(report.mods_str, "HDDT");
+        assert_eq!(report.combo, 500);
+        assert_eq!(report.n_misses, 2);
+        assert_eq!(
+            report.n300 + report.n100 + report.n50 + report.n_misses,
+            map.hit_objects.len()
+        );
+        assert!((report.accuracy - 97.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn osu_rx_depression_factor() {
+        let map = Beatmap::default();
+
+        let attributes = OsuDifficultyAttributes {
+            ar: 9.5,
+            od: 9.0,
+            aim_strain: 2.0,
+            speed_strain: 3.5,
+            n_circles: 900,
+            n_sliders: 100,
+            stars: 6.0,
+            max_combo: 1200,
+            ..Default::default()
+        };
+
+        let factor = |acc: f64| {
+            let (_, trace) = OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .mods(128)
+                .passed_objects(1000)
+                .accuracy(acc)
+                .calculate_with_trace();
+
+            trace.rx_depression_factor
+        };
+
+        let (low, mid, high) = (factor(97.0), factor(98.5), factor(100.0));
+
+        assert!(low < mid && mid < high);
+        assert!((mid - (low + high) / 2.0).abs() < 0.005);
+        assert!((high - 0.92).abs() < f64::EPSILON);
+
+        // No jump around 97%
+        let (below, at) = (factor(96.9), factor(97.0));
+        assert!(below < at);
+        assert!(at - below < 0.002);
+
+        // The lower bound is only reached at 95%
+        assert!(factor(95.3) > 0.85);
+        assert!((factor(95.0) - 0.85).abs() < 1e-9);
+        assert!((factor(90.0) - 0.85).abs() < f64::EPSILON);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_if_fc() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+        let n_objects = map.hit_objects.len();
+
+        let score = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .combo(300)
+            .n100(20)
+            .n50(3)
+            .misses(4);
+
+        let achieved = score.clone().calculate();
+        let if_fc = score.clone().if_fc();
+
+        let expected = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .combo(attributes.max_combo)
+            .n300(n_objects - 23)
+            .n100(20)
+            .n50(3)
+            .calculate();
+
+        assert_eq!(if_fc, expected);
+        assert!(if_fc.pp > achieved.pp);
+
+        // A full combo doesn't have slider breaks either
+        let with_breaks = score.clone().slider_breaks(3).if_fc();
+
+        assert_eq!(with_breaks, score.if_fc());
+        assert_eq!(with_breaks.effective_misses, 0.0);
+    }
+
+    #[test]
+    fn osu_rx_speed_weight() {
+        let map = Beatmap::default();
+
+        let attributes = OsuDifficultyAttributes {
+            ar: 10.0,
+            od: 9.0,
+            aim_strain: 3.0,
+            speed_strain: 3.5,
+            n_circles: 900,
+            n_sliders: 100,
+            stars: 6.5,
+            max_combo: 1200,
+            ..Default::default()
+        };
+
+        let calculate = |weight: Option<f64>| {
+            let mut calculator = OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .mods(128)
+                .passed_objects(1000)
+                .accuracy(98.0);
+
+            if let Some(weight) = weight {
+                calculator = calculator.rx_speed_weight(weight);
+            }
+
+            calculator.calculate().pp
+        };
+
+        assert_eq!(calculate(None), calculate(Some(0.0)));
+        assert!(calculate(Some(0.5)) > calculate(None));
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_combo_above_max() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+
+        let over_max = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .combo(attributes.max_combo + 100)
+            .n100(10)
+            .calculate();
+
+        let full_combo = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .combo(attributes.max_combo)
+            .n100(10)
+            .calculate();
+
+        assert_eq!(over_max, full_combo);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_fill_n300_from_accuracy() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).n100(10).n50(2).misses(1);
+
+        // 588 300s, 10 100s, 2 50s, and 1 miss
+        let acc = 100.0 * (6 * 588 + 2 * 10 + 2) as f64 / (6 * 601) as f64;
+
+        let state = calculator
+            .clone()
+            .fill_n300_from_accuracy(acc)
+            .unwrap()
+            .generate_state();
+
+        assert_eq!(
+            (state.n300, state.n100, state.n50, state.misses),
+            (588, 10, 2, 1)
+        );
+
+        // Partial play with fewer objects
+        let acc = 100.0 * (6 * 200 + 2 * 10 + 2) as f64 / (6 * 213) as f64;
+        let partial = calculator.clone().passed_objects(213);
+        let state = partial
+            .fill_n300_from_accuracy(acc)
+            .unwrap()
+            .generate_state();
+
+        assert_eq!(
+            (state.n300, state.n100, state.n50, state.misses),
+            (200, 10, 2, 1)
+        );
+
+        assert_eq!(
+            calculator.clone().fill_n300_from_accuracy(20.0).err(),
+            Some(ScoreStateError::UnreachableAccuracy(20.0))
+        );
+
+        assert_eq!(
+            calculator.clone().fill_n300_from_accuracy(100.0).err(),
+            Some(ScoreStateError::UnreachableAccuracy(100.0))
+        );
+
+        assert_eq!(
+            calculator.clone().fill_n300_from_accuracy(105.0).err(),
+            Some(ScoreStateError::InvalidAccuracy(105.0))
+        );
+
+        assert!(matches!(
+            calculator.fill_n300_from_accuracy(99.99),
+            Err(ScoreStateError::TooManyHitResults { .. })
+        ));
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_hit_window_accuracy() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let calculator = OsuPP::new(&map).mods(128).misses(2);
+
+        let accuracy = |mean_error: f64, unstable_rate: f64| {
+            let state = calculator
+                .clone()
+                .hit_window_accuracy(mean_error, unstable_rate)
+                .generate_state();
+
+            assert_eq!(state.n300 + state.n100 + state.n50 + state.misses, 601);
+            assert_eq!(state.misses, 2);
+
+            (6 * state.n300 + 2 * state.n100 + state.n50) as f64 / (6 * 601) as f64
+        };
+
+        let perfect = accuracy(0.0, 0.0);
+        let tight = accuracy(0.0, 120.0);
+        let loose = accuracy(0.0, 250.0);
+
+        assert!((perfect - 599.0 / 601.0).abs() < f64::EPSILON);
+        assert!(perfect > tight);
+        assert!(tight > loose);
+
+        // Being off-center on average is worse, regardless of the direction
+        assert!(accuracy(10.0, 120.0) < tight);
+        assert!((accuracy(-10.0, 120.0) - accuracy(10.0, 120.0)).abs() < f64::EPSILON);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_accuracy_for_pp() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let mods = 8;
+
+        let max_pp = OsuPP::max_pp(&map, mods).pp;
+        let calculator = OsuPP::new(&map).mods(mods);
+
+        let acc = calculator.clone().accuracy_for_pp(max_pp).unwrap();
+        assert!((acc - 100.0).abs() < f64::EPSILON);
+
+        assert!(calculator.clone().accuracy_for_pp(max_pp + 1.0).is_none());
+
+        let target = 0.8 * max_pp;
+        let acc = calculator.clone().accuracy_for_pp(target).unwrap();
+        let reached = calculator.clone().accuracy(acc).calculate().pp;
+        assert!(reached >= target);
+        assert!(acc < 100.0);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_combo_for_pp() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).mods(8 + 1024).calculate().difficulty;
+        let max_combo = attributes.max_combo;
+
+        let calculator = OsuPP::new(&map)
+            .mods(8 + 1024)
+            .attributes(attributes)
+            .misses(2)
+            .accuracy(97.0);
+
+        // The search requires pp to not decrease with increasing combo
+        let mut prev = 0.0;
+
+        for combo in (0..=max_combo).step_by(7).chain(Some(max_combo)) {
+            let pp = calculator.clone().combo(combo).calculate().pp;
+            assert!(pp >= prev, "combo {}: {} < {}", combo, pp, prev);
+            prev = pp;
+        }
+
+        let target = calculator.clone().combo(500).calculate().pp;
+        let combo = calculator.clone().combo_for_pp(target).unwrap();
+
+        assert!(combo <= 500);
+        assert!(calculator.clone().combo(combo).calculate().pp >= target);
+        assert!(calculator.clone().combo(combo - 1).calculate().pp < target);
+
+        assert!(calculator.combo_for_pp(prev + 1.0).is_none());
+    }
+
+    #[test]
+    fn osu_degenerate_attributes_finite() {
+        let map = Beatmap::default();
+
+        let degenerate = [
+            OsuDifficultyAttributes::default(),
+            OsuDifficultyAttributes {
+                aim_strain: f64::INFINITY,
+                speed_strain: f64::NAN,
+                flashlight_rating: f64::INFINITY,
+                n_circles: 100,
+                ..Default::default()
+            },
+        ];
+
+        for attributes in degenerate.iter() {
+            for &mods in &[0, 8 + 64 + 1024, 128, 8192, 1 + 2 + 4 + 4096] {
+                for &misses in &[0, 5] {
+                    let (performance, trace) = OsuPP::new(&map)
+                        .attributes(attributes.clone())
+                        .passed_objects(100)
+                        .mods(mods)
+                        .misses(misses)
+                        .calculate_with_trace();
+
+                    assert!(
+                        performance.pp.is_finite(),
+                        "mods={} misses={}",
+                        mods,
+                        misses
+                    );
+                    assert!(performance.pp_aim.is_finite());
+                    assert!(performance.pp_speed.is_finite());
+                    assert!(performance.pp_acc.is_finite());
+                    assert!(performance.pp_flashlight.is_finite());
+                    assert!(trace.pp.is_finite());
+                }
+            }
+        }
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_relax_hr_cs_bonus() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let map = map.with_difficulty(map.ar, map.od, 5.5, map.hp);
+        let mods = 128 + 16; // RXHR
+
+        let nm_attributes = OsuPP::new(&map).mods(128).calculate().difficulty;
+        assert!(nm_attributes.cs < 6.0);
+
+        let (performance, trace) = OsuPP::new(&map).mods(mods).calculate_with_trace();
+        let attributes = performance.difficulty;
+        assert!((attributes.cs - 7.15).abs() < 1e-4);
+
+        // Same attributes but the CS just below the bonus threshold
+        let without_bonus = OsuDifficultyAttributes {
+            cs: 6.0,
+            ..attributes.clone()
+        };
+
+        let (_, trace_without) = OsuPP::new(&map)
+            .mods(mods)
+            .attributes(without_bonus)
+            .calculate_with_trace();
+
+        let bonus = 1.03 + (attributes.cs - 6.0) / 20.0;
+        let ratio = trace.aim.scaled / trace_without.aim.scaled;
+        assert!((ratio - bonus).abs() < 1e-9);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_rx_ht_multipliers() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculate = |mods: u32, multipliers: Option<(f64, f64)>| {
+            let mut calculator = OsuPP::new(&map).mods(mods).accuracy(98.0);
+
+            if let Some((aim, acc)) = multipliers {
+                calculator = calculator.rx_ht_multipliers(aim, acc);
+            }
+
+            calculator.calculate()
+        };
+
+        let rx_ht = calculate(128 + 256, None);
+        let rx_ht_boosted = calculate(128 + 256, Some((1.0, 1.2)));
+
+        assert!((rx_ht_boosted.pp_acc / rx_ht.pp_acc - 1.2).abs() < 1e-9);
+        assert_eq!(rx_ht_boosted.pp_aim, rx_ht.pp_aim);
+        assert!(rx_ht_boosted.pp > rx_ht.pp);
+
+        // Neither nomod RX nor HT without RX are affected
+        assert_eq!(calculate(128, Some((0.5, 1.2))), calculate(128, None));
+        assert_eq!(calculate(256, Some((0.5, 1.2))), calculate(256, None));
+
+        let incompatible = OsuPP::new(&map)
+            .mods(128 + 256 + 64)
+            .rx_ht_multipliers(1.0, 1.2)
+            .try_calculate();
+
+        assert_eq!(
+            incompatible,
+            Err(ScoreStateError::IncompatibleMods(128 + 256 + 64))
+        );
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_relax_acc_profile() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let nerf = RelaxAccProfile::new(0.9, 0.8);
+
+        let calculate = |mods: u32, profile: Option<RelaxAccProfile>| {
+            let mut calculator = OsuPP::new(&map).mods(mods).accuracy(98.0);
+
+            if let Some(profile) = profile {
+                calculator = calculator.relax_acc_profile(profile);
+            }
+
+            calculator.calculate()
+        };
+
+        let rx = calculate(128, None);
+        assert_eq!(calculate(128, Some(RelaxAccProfile::default())), rx);
+
+        let rx_nerfed = calculate(128, Some(nerf));
+        assert!(rx_nerfed.pp_acc < rx.pp_acc);
+        assert!(rx_nerfed.pp < rx.pp);
+        assert_eq!(rx_nerfed.pp_aim, rx.pp_aim);
+
+        assert_eq!(calculate(0, Some(nerf)), calculate(0, None));
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_combo_above_max_combo() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+        let max_combo = attributes.max_combo;
+
+        let calculator = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .combo(max_combo + 500)
+            .misses(3);
+
+        let (performance, trace) = calculator.clone().calculate_with_trace();
+
+        assert_eq!(performance.state.max_combo, max_combo);
+        assert!((trace.effective_misses - 3.0).abs() < f64::EPSILON);
+
+        let clamped = OsuPP::new(&map)
+            .attributes(attributes)
+            .combo(max_combo)
+            .misses(3)
+            .calculate();
+
+        assert_eq!(performance, clamped);
+
+        assert_eq!(
+            calculator.try_calculate(),
+            Err(ScoreStateError::ComboTooHigh {
+                combo: max_combo + 500,
+                max_combo,
+            })
+        );
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_scoring_mode() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+        assert!(attributes.n_sliders > 0);
+
+        let calculator = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .combo(700)
+            .n300(590)
+            .n100(10)
+            .misses(1);
+
+        let stable = calculator
+            .clone()
+            .scoring_mode(ScoringMode::Stable)
+            .calculate();
+
+        let lazer = calculator
+            .clone()
+            .scoring_mode(ScoringMode::Lazer)
+            .calculate();
+
+        assert_eq!(stable, calculator.calculate());
+        assert!((stable.pp - lazer.pp).abs() > 1e-3);
+
+        // Without sliders there are no slider tails to account for
+        let circles_only = OsuDifficultyAttributes {
+            n_circles: attributes.n_circles + attributes.n_sliders,
+            n_sliders: 0,
+            ..attributes
+        };
+
+        let calculator = OsuPP::from_attributes(circles_only)
+            .combo(700)
+            .n300(590)
+            .n100(10)
+            .misses(1);
+
+        let stable = calculator.clone().calculate();
+        let lazer = calculator.scoring_mode(ScoringMode::Lazer).calculate();
+
+        assert_eq!(stable, lazer);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_scoring_mode_delta() {
+        use super::super::STARS_CALCULATIONS;
+
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        assert!(map.n_sliders > 100);
+
+        let calculator = OsuPP::new(&map).combo(700).n300(590).n100(10).misses(1);
+
+        STARS_CALCULATIONS.with(|count| count.set(0));
+        let delta = calculator.clone().scoring_mode_delta();
+        assert_eq!(STARS_CALCULATIONS.with(|count| count.get()), 1);
+
+        let stable = calculator.clone().calculate().pp;
+        let lazer = calculator.scoring_mode(ScoringMode::Lazer).calculate().pp;
+
+        assert!(delta.abs() > 1e-3);
+        assert!((delta - (lazer - stable)).abs() < 1e-9);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_relax_flashlight() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuStars::new(&map).mods(1024).calculate();
+
+        let calculate = |mods: u32, exponent: Option<f64>, combo: usize| {
+            let mut calculator = OsuPP::new(&map)
+                .mods(mods)
+                .attributes(attributes.clone())
+                .combo(combo)
+                .misses(1)
+                .accuracy(98.0);
+
+            if let Some(exponent) = exponent {
+                calculator = calculator.rx_flashlight_combo_exponent(exponent);
+            }
+
+            calculator.calculate().pp_flashlight
+        };
+
+        // By default the flashlight value of RXFL is the same as for FL
+        let fl = calculate(1024, None, 500);
+        let rx_fl = calculate(128 + 1024, None, 500);
+
+        assert!(fl > 0.0);
+        assert!((rx_fl - fl).abs() < f64::EPSILON);
+
+        // A higher exponent punishes combo breaks more for RX only
+        assert!(calculate(128 + 1024, Some(1.2), 500) < rx_fl);
+        assert!((calculate(1024, Some(1.2), 500) - fl).abs() < f64::EPSILON);
+
+        // Full combos are unaffected
+        let max_combo = attributes.max_combo;
+        let fc = calculate(128 + 1024, None, max_combo);
+        assert!((calculate(128 + 1024, Some(1.2), max_combo) - fc).abs() < f64::EPSILON);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_ar_od_override() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+        assert!(attributes.od < 10.0 && attributes.ar < 11.0);
+
+        let calculator = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .accuracy(98.0);
+
+        let regular = calculator.clone().calculate();
+        let od10 = calculator.clone().od(10.0).calculate();
+
+        assert!(od10.pp_acc > regular.pp_acc);
+        assert_eq!(od10.difficulty.od, 10.0);
+        assert_eq!(od10.difficulty.aim_strain, attributes.aim_strain);
+        assert_eq!(calculator.clone().od(12.0).calculate(), od10);
+
+        let ar11 = calculator.ar(11.0).calculate();
+
+        assert!(ar11.pp_aim > regular.pp_aim);
+        assert_eq!(ar11.pp_acc, regular.pp_acc);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_relax_nf_penalty() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let attributes = OsuStars::new(&map).calculate();
+
+        let calculate = |mods: u32, penalize: bool| {
+            OsuPP::new(&map)
+                .mods(mods)
+                .attributes(attributes.clone())
+                .misses(3)
+                .relax_nf_penalty(penalize)
+                .calculate_with_trace()
+        };
+
+        let (rx, _) = calculate(128, true);
+        let (rx_nf, trace) = calculate(128 + 1, true);
+
+        assert!((trace.nf_multiplier - 0.94).abs() < 1e-9);
+        assert!((rx_nf.pp - rx.pp * 0.94).abs() < 1e-9);
+
+        let (rx_nf, trace) = calculate(128 + 1, false);
+
+        assert_eq!(trace.nf_multiplier, 1.0);
+        assert!((rx_nf.pp - rx.pp).abs() < f64::EPSILON);
+
+        // Scores without RX are always penalized
+        assert_eq!(calculate(1, false).0, calculate(1, true).0);
+        assert!(calculate(1, false).0.pp < calculate(0, false).0.pp);
+    }
+
+    #[test]
+    fn osu_hit_distribution_bias() {
+        let attributes = OsuDifficultyAttributes {
+            n_circles: 1000,
+            max_combo: 1000,
+            ..Default::default()
+        };
+
+        let generate = |bias: HitDistributionBias| {
+            let calculator = OsuPP::from_attributes(attributes.clone())
+                .hit_distribution_bias(bias)
+                .accuracy(98.0);
+
+            let n300 = calculator.n300.unwrap();
+            let n100 = calculator.n100.unwrap();
+            let n50 = calculator.n50.unwrap();
+
+            assert_eq!(n300 + n100 + n50, 1000);
+            assert!((calculator.acc.unwrap() - 0.98).abs() < 0.001);
+
+            (n300, n100, n50)
+        };
+
+        let (_, _, n50) = generate(HitDistributionBias::FewerFifties);
+        assert!(n50 <= 1);
+
+        let default = OsuPP::from_attributes(attributes.clone()).accuracy(98.0);
+        assert_eq!(default.n50, Some(n50));
+
+        let (_, n100, n50) = generate(HitDistributionBias::Realistic);
+        assert!(n50 > 0);
+        assert!(n100 >= 10 * n50);
+        assert!(n100 < 10 * n50 + 11);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_accuracy_mode() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let n_objects = map.hit_objects.len();
+        let n_sliders = map.n_sliders as usize;
+
+        let generate = |mode: AccuracyMode| {
+            let calculator = OsuPP::new(&map)
+                .misses(2)
+                .accuracy_mode(mode)
+                .accuracy(98.0);
+
+            let n300 = calculator.n300.unwrap();
+            let n100 = calculator.n100.unwrap();
+            let n50 = calculator.n50.unwrap();
+            assert_eq!(n300 + n100 + n50 + 2, n_objects);
+
+            (n300, n100, n50)
+        };
+
+        let (std300, std100, std50) = generate(AccuracyMode::Standard);
+        let std_acc = (6 * std300 + 2 * std100 + std50) as f64 / (6 * n_objects) as f64;
+        assert!((std_acc - 0.98).abs() < 0.001);
+
+        // Slider heads make up for some points so more 100s are needed
+        let (v2_300, v2_100, v2_50) = generate(AccuracyMode::ScoreV2);
+        assert!(v2_300 < std300);
+        assert!(v2_100 > std100);
+
+        let numerator = 6 * v2_300 + 2 * v2_100 + v2_50 + 6 * (n_sliders - 2);
+        let v2_acc = numerator as f64 / (6 * (n_objects + n_sliders)) as f64;
+        assert!((v2_acc - 0.98).abs() < 0.001);
+
+        // Same hitresults are worth more accuracy with ScoreV2
+        let std_state = |mode: AccuracyMode, mods: u32| {
+            OsuPP::new(&map)
+                .mods(mods)
+                .accuracy_mode(mode)
+                .n300(std300)
+                .n100(std100)
+                .n50(std50)
+                .misses(2)
+                .calculate()
+                .pp
+        };
+
+        for &mods in &[0, 128, 8192] {
+            let standard = std_state(AccuracyMode::Standard, mods);
+            let score_v2 = std_state(AccuracyMode::ScoreV2, mods);
+
+            assert!(standard.is_finite() && standard > 0.0, "mods {}", mods);
+            assert!(score_v2.is_finite(), "mods {}", mods);
+            assert!(score_v2 >= standard, "mods {}", mods);
+
+            let generated = OsuPP::new(&map)
+                .mods(mods)
+                .misses(2)
+                .accuracy_mode(AccuracyMode::ScoreV2)
+                .accuracy(98.0)
+                .calculate()
+                .pp;
+
+            assert!(generated.is_finite() && generated > 0.0, "mods {}", mods);
+        }
+    }
+
+    #[cfg(all(
+        debug_assertions,
+        not(any(feature = "async_tokio", feature = "async_std"))
+    ))]
+    #[test]
+    #[should_panic(expected = "more hitresults than objects")]
+    fn osu_inconsistent_state_debug_assert() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        // More 300s than the map has objects
+        let _ = OsuPP::new(&map).n300(700).calculate();
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_slider_breaks() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).combo(500).misses(1);
+
+        let (estimated, estimated_trace) = calculator.clone().calculate_with_trace();
+        let (explicit, explicit_trace) = calculator.slider_breaks(3).calculate_with_trace();
 
-        if attributes.n_sliders > 0 {
-            // * We assume 15% of sliders in a map are difficult since
-            // * there's no way to tell from the performance calculator.
-            let estimate_difficult_sliders = attributes.n_sliders as f64 * 0.15;
+        // The combo alone suggests less than two misses
+        assert!(estimated_trace.effective_misses < 2.0);
+        assert!((explicit_trace.effective_misses - 4.0).abs() < f64::EPSILON);
+        assert!(explicit.pp < estimated.pp);
 
-            let non_300s = self.total_hits - self.n300 as f64;
-            let missing_combo = attributes.max_combo - self.combo.unwrap_or(attributes.max_combo);
+        // No slider breaks leaves only the actual misses, even for a low combo
+        let (_, trace) = OsuPP::new(&map)
+            .combo(100)
+            .misses(1)
+            .slider_breaks(0)
+            .calculate_with_trace();
 
-            let estimate_slider_ends_dropped = non_300s
-                .min(missing_combo as f64)
-                .clamp(0.0, estimate_difficult_sliders);
+        assert!((trace.effective_misses - 1.0).abs() < f64::EPSILON);
+    }
 
-            let base = 1.0 - estimate_slider_ends_dropped / estimate_difficult_sliders;
-            let slider_nerf_factor =
-                (1.0 - attributes.slider_factor) * base * base * base + attributes.slider_factor;
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_try_calculate() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
 
-            aim_value *= slider_nerf_factor;
-        }
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+        let n_objects = map.hit_objects.len();
 
-        aim_value *= self.acc;
-        aim_value *= 0.98 + attributes.od * attributes.od / 2500.0;
+        let valid = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .n100(10)
+            .misses(1)
+            .try_calculate();
 
-        aim_value
-    }
+        assert!(valid.is_ok());
 
-    fn compute_speed_value(&self) -> f64 {
-        let attributes = &self.attributes;
-        let total_hits = self.total_hits;
+        let over_counted = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .n300(n_objects)
+            .n100(10)
+            .try_calculate();
 
-        let mut speed_value =
-            (5.0 * (attributes.speed_strain / 0.0675).max(1.0) - 4.0).powi(3) / 100_000.0;
+        assert_eq!(
+            over_counted,
+            Err(ScoreStateError::TooManyHitResults {
+                total: n_objects + 10,
+                n_objects,
+            })
+        );
 
-        // Longer maps are worth more
-        let len_bonus = 0.95
-            + 0.4 * (total_hits / 2000.0).min(1.0)
-            + (total_hits > 2000.0) as u8 as f64 * 0.5 * (total_hits / 2000.0).log10();
-        speed_value *= len_bonus;
+        let impossible_combo = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .combo(attributes.max_combo + 1)
+            .try_calculate();
 
-        // Penalize misses
-        let effective_misses = self.effective_misses as f64;
-        if effective_misses > 0.0 {
-            speed_value *=
-                calculate_miss_penalty(effective_misses, attributes.aim_difficult_strain_count);
+        assert_eq!(
+            impossible_combo,
+            Err(ScoreStateError::ComboTooHigh {
+                combo: attributes.max_combo + 1,
+                max_combo: attributes.max_combo,
+            })
+        );
+
+        for &mods in &[128 + 8192, 64 + 256] {
+            let incompatible = OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .mods(mods)
+                .try_calculate();
+
+            assert_eq!(incompatible, Err(ScoreStateError::IncompatibleMods(mods)));
         }
+    }
 
-        // AR bonus
-        let ar_factor = if self.mods.rx() {
-            if attributes.ar > 10.7 {
-                0.4 * (attributes.ar - 10.7)
-            } else {
-                0.0
-            }
-        } else {
-            if attributes.ar > 10.33 {
-                0.3 * (attributes.ar - 10.33)
-            } else {
-                0.0
-            }
-        };
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_accuracy_out_of_range() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
 
-        speed_value *= 1.0 + ar_factor * len_bonus; // * Buff for longer maps with high AR.
+        let attributes = OsuPP::new(&map).calculate().difficulty;
+        let n_objects = map.hit_objects.len();
 
-        // HD bonus (this would include the Blinds mod but it's currently not representable)
-        let hd_factor = match self.mods.rx() {
-            true => (0.05, 11.0),
-            _ => (0.04, 12.0),
+        let calculate = |acc: f64| {
+            OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .misses(2)
+                .accuracy(acc)
         };
 
-        if self.mods.hd() {
-            speed_value *= 1.0 + hd_factor.0 * (hd_factor.1 - attributes.ar);
-        }
+        let min = calculate(0.0).generate_state();
+        assert_eq!((min.n300, min.n100, min.n50), (0, 0, n_objects - 2));
 
-        // Scaling the speed value with accuracy and OD
-        let od_factor = 0.95 + attributes.od * attributes.od / 750.0;
-        let acc_factor = self.acc.powf((14.5 - attributes.od.max(8.0)) / 2.0);
-        speed_value *= od_factor * acc_factor;
+        let max = calculate(100.0).generate_state();
+        assert_eq!((max.n300, max.n100, max.n50), (n_objects - 2, 0, 0));
 
-        // Penalize n50s
-        let n50_factor: f64 = if self.mods.ap() {
-            0.96
-        } else {
-            0.98
-        };
+        assert_eq!(calculate(-1.0).generate_state(), min);
+        assert_eq!(calculate(150.0).generate_state(), max);
+
+        assert!(calculate(0.0).try_calculate().is_ok());
+        assert!(calculate(100.0).try_calculate().is_ok());
 
-        speed_value *= n50_factor.powf(
-            (self.n50 as f64 >= total_hits / 500.0) as u8 as f64
-                * (self.n50 as f64 - total_hits / 500.0),
+        assert_eq!(
+            calculate(-1.0).try_calculate(),
+            Err(ScoreStateError::InvalidAccuracy(-1.0))
+        );
+        assert_eq!(
+            calculate(150.0).try_calculate(),
+            Err(ScoreStateError::InvalidAccuracy(150.0))
         );
 
-        speed_value
+        // Hitresults set afterwards replace the invalid accuracy
+        assert!(calculate(150.0).n300(n_objects - 2).try_calculate().is_ok());
+        assert!(calculate(-1.0).n50(n_objects - 2).try_calculate().is_ok());
+        assert!(calculate(-1.0).misses(1).try_calculate().is_ok());
+        assert!(calculate(150.0).state(max).try_calculate().is_ok());
+        assert!(calculate(150.0).accuracy(99.0).try_calculate().is_ok());
     }
 
-    fn compute_accuracy_value(&self) -> f64 {
-        let attributes = &self.attributes;
-        let total_hits = self.total_hits;
-        let n_circles = attributes.n_circles as f64;
-        let n300 = self.n300 as f64;
-        let n100 = self.n100 as f64;
-        let n50 = self.n50 as f64;
+    #[test]
+    fn osu_effective_clock_rate() {
+        let map = Beatmap::default();
 
-        let better_acc_percentage = (n_circles > 0.0) as u8 as f64
-            * (((n300 - (total_hits - n_circles)) * 6.0 + n100 * 2.0 + n50) / (n_circles * 6.0))
-                .max(0.0);
+        assert_eq!(OsuPP::new(&map).effective_clock_rate(), 1.0);
+        assert_eq!(OsuPP::new(&map).mods(64).effective_clock_rate(), 1.5);
+        assert_eq!(OsuPP::new(&map).mods(256).effective_clock_rate(), 0.75);
 
-        let mut acc_value = 1.52163_f64.powf(attributes.od) * better_acc_percentage.powi(24) * 2.83;
+        let calculator = OsuPP::new(&map).mods(64).clock_rate(1.33);
+        assert_eq!(calculator.effective_clock_rate(), 1.33);
+    }
 
-        // Bonus for many hitcircles
-        acc_value *= ((n_circles as f64 / 1000.0).powf(0.3)).min(1.15);
+    #[test]
+    fn osu_slider_velocity_bonus() {
+        let map = Beatmap::default();
 
-        // HD bonus (this would include the Blinds mod but it's currently not representable)
-        if self.mods.hd() {
-            acc_value *= 1.08;
-        }
+        let calculate = |avg_slider_velocity: f64, bonus: f64| {
+            let attributes = OsuDifficultyAttributes {
+                ar: 9.5,
+                od: 9.0,
+                aim_strain: 3.0,
+                speed_strain: 2.5,
+                n_circles: 600,
+                n_sliders: 400,
+                stars: 6.0,
+                max_combo: 1500,
+                avg_slider_velocity,
+                ..Default::default()
+            };
 
-        // FL bonus
-        if self.mods.fl() {
-            acc_value *= 1.02;
-        }
+            OsuPP::new(&map)
+                .attributes(attributes)
+                .slider_velocity_bonus(bonus)
+                .passed_objects(1000)
+                .calculate()
+                .pp_aim
+        };
 
-        acc_value
+        assert_eq!(calculate(2.5, 0.0), calculate(0.5, 0.0));
+        assert_eq!(calculate(0.5, 0.1), calculate(0.5, 0.0));
+        assert!(calculate(2.5, 0.1) > calculate(2.5, 0.0));
     }
 
-    fn compute_flashlight_value(&self) -> f64 {
-        if !self.mods.fl() {
-            return 0.0;
-        }
+    #[test]
+    fn osu_difficult_slider_ratio() {
+        let map = Beatmap::default();
 
-        let attributes = &self.attributes;
-        let total_hits = self.total_hits;
+        let mut attributes = OsuDifficultyAttributes {
+            ar: 9.5,
+            od: 9.0,
+            aim_strain: 3.0,
+            speed_strain: 2.5,
+            slider_factor: 0.9,
+            n_circles: 600,
+            n_sliders: 400,
+            stars: 6.0,
+            max_combo: 1500,
+            ..Default::default()
+        };
 
-        // TD penalty
-        let raw_flashlight = if self.mods.td() {
-            attributes.flashlight_rating.powf(0.8)
-        } else {
-            attributes.flashlight_rating
+        attributes.aim_difficult_strain_count = 100.0;
+
+        let calculate = |ratio: f64, combo: usize| {
+            OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .difficult_slider_ratio(ratio)
+                .passed_objects(1000)
+                .n300(960)
+                .n100(40)
+                .combo(combo)
+                .calculate()
+                .pp_aim
         };
 
-        let mut flashlight_value = raw_flashlight * raw_flashlight * 25.0;
+        // Without dropped combo there is no slider nerf
+        assert_eq!(calculate(0.05, 1500), calculate(0.5, 1500));
+        assert!(calculate(0.0, 1400) > calculate(1.0, 1400));
 
-        // Add an additional bonus for HDFL
-        if self.mods.hd() {
-            flashlight_value *= 1.3;
-        }
+        // Dropped slider ends are spread across more difficult sliders
+        assert!(calculate(0.05, 1400) < calculate(0.15, 1400));
+        assert!(calculate(0.15, 1400) < calculate(0.5, 1400));
+        assert_eq!(calculate(2.0, 1400), calculate(1.0, 1400));
+    }
 
-        // Penalize misses by assessing # of misses relative to the total # of objects.
-        // Default a 3% reduction for any # of misses
-        let effective_misses = self.effective_misses as f64;
-        if effective_misses > 0.0 {
-            flashlight_value *= 0.97
-                * (1.0 - (effective_misses / total_hits).powf(0.775))
-                    .powf(effective_misses.powf(0.875));
-        }
+    #[test]
+    fn osu_len_bonus_and_miss_penalties() {
+        let map = Beatmap::default();
 
-        // Combo scaling
-        if let Some(combo) = self.combo.filter(|_| attributes.max_combo > 0) {
-            flashlight_value *= ((combo as f64 / attributes.max_combo as f64).powf(0.8)).min(1.0);
-        }
+        let attributes = OsuDifficultyAttributes {
+            ar: 9.5,
+            od: 9.0,
+            aim_strain: 3.0,
+            speed_strain: 2.5,
+            n_circles: 2000,
+            n_sliders: 1000,
+            stars: 6.0,
+            max_combo: 4000,
+            ..Default::default()
+        };
 
-        // Account for shorter maps having a higher ratio of 0 combo/100 combo flashlight radius
-        flashlight_value *= 0.7
-            + 0.1 * (total_hits / 200.0).min(1.0)
-            + (total_hits > 200.0) as u8 as f64 * (0.2 * ((total_hits - 200.0) / 200.0).min(1.0));
+        let fc = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .passed_objects(3000)
+            .calculate();
 
-        // Scale the aim value with accuracy _slightly_
-        flashlight_value *= 0.5 + self.acc / 2.0;
+        assert!(fc.len_bonus > 1.0);
+        assert_eq!(fc.aim_miss_penalty, 1.0);
+        assert_eq!(fc.speed_miss_penalty, 1.0);
 
-        // It is important to also consider accuracy difficulty when doing that
-        flashlight_value *= 0.98 + attributes.od * attributes.od / 2500.0;
+        let misses = OsuPP::new(&map)
+            .attributes(attributes.clone())
+            .passed_objects(3000)
+            .misses(5)
+            .calculate();
 
-        flashlight_value
+        assert!(misses.aim_miss_penalty < 1.0);
+        assert!(misses.speed_miss_penalty < 1.0);
+
+        let empty = OsuPP::new(&map)
+            .attributes(attributes)
+            .passed_objects(0)
+            .calculate();
+
+        assert_eq!(empty.len_bonus, 1.0);
+        assert_eq!(empty.aim_miss_penalty, 1.0);
     }
-}
 
-fn calculate_miss_penalty(n_misses: f64, difficult_strain_count: f64) -> f64 {
-    // Miss penalty assumes that a player will miss on the hardest parts of a map,
-    // so we use the amount of relatively difficult sections to adjust miss penalty
-    // to make it more punishing on maps with lower amount of hard sections.
-    0.94 / ((n_misses / (2.0 * f64::sqrt(difficult_strain_count))) + 1.0)
-}
+    #[test]
+    fn osu_rx_overrides() {
+        let map = Beatmap {
+            beatmap_id: 1808605,
+            ..Default::default()
+        };
 
-fn calculate_effective_misses(
-    attributes: &OsuDifficultyAttributes,
-    combo: Option<usize>,
-    n_misses: usize,
-    total_hits: f64,
-) -> usize {
-    // * Guess the number of misses + slider breaks from combo
-    let mut combo_based_misses: f64 = 0.0;
+        let attributes = OsuDifficultyAttributes {
+            ar: 9.5,
+            od: 9.0,
+            aim_strain: 3.0,
+            speed_strain: 2.5,
+            n_circles: 800,
+            n_sliders: 200,
+            stars: 6.0,
+            max_combo: 1200,
+            ..Default::default()
+        };
 
-    if attributes.n_sliders > 0 {
-        let full_combo_threshold = attributes.max_combo as f64 - 0.1 * attributes.n_sliders as f64;
+        let calculate = |overrides: Option<HashMap<i32, f64>>| {
+            let mut calculator = OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .mods(128)
+                .passed_objects(1000);
 
-        let f64_combo = combo.map(|c| c as f64);
+            if let Some(overrides) = overrides {
+                calculator = calculator.with_rx_overrides(overrides);
+            }
 
-        if let Some(combo) = f64_combo.filter(|&c| c < full_combo_threshold) {
-            combo_based_misses = full_combo_threshold / combo.max(1.0);
-        }
+            calculator.calculate_with_trace().1
+        };
+
+        let default = calculate(None);
+        assert_eq!(default.map_multiplier, 0.7);
+
+        let overridden = calculate(Some(vec![(1808605, 0.5)].into_iter().collect()));
+        assert_eq!(overridden.map_multiplier, 0.5);
+        assert!(overridden.pp < default.pp);
+
+        let unrelated = calculate(Some(vec![(1, 0.5)].into_iter().collect()));
+        assert_eq!(unrelated, default);
     }
 
-    // * Clamp misscount since it's derived from combo and can be
-    // * higher than total hits and that breaks some calculations
-    combo_based_misses = combo_based_misses.min(total_hits);
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_unset_misses() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
 
-    n_misses.max(combo_based_misses.floor() as usize)
-}
+        let max_combo = map.max_combo();
 
-/// Abstract type to provide flexibility when passing difficulty attributes to a performance calculation.
-pub trait OsuAttributeProvider {
-    /// Provide the actual difficulty attributes.
-    fn attributes(self) -> Option<OsuDifficultyAttributes>;
-}
+        let derived = OsuPP::new(&map)
+            .combo(max_combo / 4)
+            .n_misses(None)
+            .accuracy(97.0);
 
-impl OsuAttributeProvider for OsuDifficultyAttributes {
-    #[inline]
-    fn attributes(self) -> Option<OsuDifficultyAttributes> {
-        Some(self)
-    }
-}
+        assert_eq!(derived.n_misses, 3);
 
-impl OsuAttributeProvider for OsuPerformanceAttributes {
-    #[inline]
-    fn attributes(self) -> Option<OsuDifficultyAttributes> {
-        Some(self.difficulty)
-    }
-}
+        let zero = OsuPP::new(&map)
+            .combo(max_combo / 4)
+            .n_misses(Some(0))
+            .accuracy(97.0);
 
-impl OsuAttributeProvider for DifficultyAttributes {
-    #[inline]
-    fn attributes(self) -> Option<OsuDifficultyAttributes> {
-        #[allow(irrefutable_let_patterns)]
-        if let Self::Osu(attributes) = self {
-            Some(attributes)
-        } else {
-            None
-        }
-    }
-}
+        assert_eq!(zero.n_misses, 0);
 
-impl OsuAttributeProvider for PerformanceAttributes {
-    #[inline]
-    fn attributes(self) -> Option<OsuDifficultyAttributes> {
-        #[allow(irrefutable_let_patterns)]
-        if let Self::Osu(attributes) = self {
-            Some(attributes.difficulty)
-        } else {
-            None
-        }
+        let full_combo = OsuPP::new(&map)
+            .combo(max_combo)
+            .n_misses(None)
+            .accuracy(97.0);
+
+        assert_eq!(full_combo.n_misses, 0);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::Beatmap;
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_from_attributes() {
+        // The map is dropped before calculating purely from the attributes
+        let (attributes, expected) = {
+            let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+            let attributes = OsuStars::new(&map).mods(8).calculate();
+            let expected = OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .mods(8)
+                .combo(500)
+                .misses(2)
+                .accuracy(97.0)
+                .calculate();
+
+            (attributes, expected)
+        };
+
+        let result = OsuPP::from_attributes(attributes)
+            .mods(8)
+            .combo(500)
+            .misses(2)
+            .accuracy(97.0)
+            .calculate();
+
+        assert_eq!(result, expected);
+    }
 
     #[test]
-    fn osu_only_accuracy() {
+    fn osu_blinds() {
         let map = Beatmap::default();
 
-        let total_objects = 1234;
-        let target_acc = 97.5;
+        let attributes = OsuDifficultyAttributes {
+            ar: 10.3,
+            od: 9.0,
+            hp: 5.0,
+            aim_strain: 3.0,
+            speed_strain: 2.5,
+            n_circles: 800,
+            n_sliders: 200,
+            stars: 6.0,
+            max_combo: 1200,
+            ..Default::default()
+        };
 
-        let calculator = OsuPP::new(&map)
-            .passed_objects(total_objects)
-            .accuracy(target_acc);
+        let calculate = |mods: u32| {
+            OsuPP::new(&map)
+                .attributes(attributes.clone())
+                .mods(mods)
+                .passed_objects(1000)
+                .accuracy(99.0)
+                .calculate()
+        };
 
-        let numerator = 6 * calculator.n300.unwrap_or(0)
-            + 2 * calculator.n100.unwrap_or(0)
-            + calculator.n50.unwrap_or(0);
-        let denominator = 6 * total_objects;
-        let acc = 100.0 * numerator as f64 / denominator as f64;
+        let nomod = calculate(0);
+        let hidden = calculate(u32::HD);
+        let blinds = calculate(u32::BL);
+        let both = calculate(u32::BL | u32::HD);
 
-        assert!(
-            (target_acc - acc).abs() < 1.0,
-            "Expected: {} | Actual: {}",
-            target_acc,
-            acc
+        assert!(blinds.pp_aim > nomod.pp_aim);
+        assert!(blinds.pp_aim > hidden.pp_aim);
+
+        // HD is ignored when BL is enabled
+        let both = OsuPerformanceAttributes {
+            mods: u32::BL,
+            ..both
+        };
+        assert_eq!(both, blinds);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_generate_state() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let calculator = OsuPP::new(&map).misses(2).accuracy(97.5);
+        let state = calculator.generate_state();
+
+        assert_eq!(
+            state.n300 + state.n100 + state.n50 + state.misses,
+            map.hit_objects.len()
         );
+        assert_eq!(state.misses, 2);
+        assert_eq!(state.max_combo, map.max_combo());
+
+        let from_state = OsuPP::new(&map).state(state).calculate();
+        assert_eq!(from_state.pp, calculator.calculate().pp);
     }
 
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
     #[test]
-    fn osu_accuracy_and_n50() {
-        let map = Beatmap::default();
+    fn osu_clock_rate_mismatch() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
 
-        let total_objects = 1234;
-        let target_acc = 97.5;
-        let n50 = 30;
+        let ht_attributes = OsuStars::new(&map).clock_rate(0.75).calculate();
 
-        let calculator = OsuPP::new(&map)
-            .passed_objects(total_objects)
-            .n50(n50)
-            .accuracy(target_acc);
+        let result = OsuPP::new(&map)
+            .attributes(ht_attributes.clone())
+            .clock_rate(1.5)
+            .calculate();
 
-        assert!(
-            (calculator.n50.unwrap() as i32 - n50 as i32).abs() <= 4,
-            "Expected: {} | Actual: {}",
-            n50,
-            calculator.n50.unwrap()
-        );
+        let expected = OsuPP::new(&map).clock_rate(1.5).calculate();
 
-        let numerator = 6 * calculator.n300.unwrap_or(0)
-            + 2 * calculator.n100.unwrap_or(0)
-            + calculator.n50.unwrap_or(0);
-        let denominator = 6 * total_objects;
-        let acc = 100.0 * numerator as f64 / denominator as f64;
+        assert_ne!(result.difficulty, ht_attributes);
+        assert_eq!(result, expected);
 
-        assert!(
-            (target_acc - acc).abs() < 1.0,
-            "Expected: {} | Actual: {}",
-            target_acc,
-            acc
-        );
+        let reused = OsuPP::new(&map)
+            .attributes(ht_attributes.clone())
+            .clock_rate(0.75)
+            .calculate();
+
+        assert_eq!(reused.difficulty, ht_attributes);
     }
 
     #[test]
@@ -839,10 +4116,10 @@ mod test {
             .passed_objects(total_objects)
             .n300(n300)
             .n100(n100)
-            .n50(n50)
-            .assert_hitresults(attributes);
+            .n50(n50);
 
-        let n_objects = calculator.n300 + calculator.n100 + calculator.n50;
+        let inner = calculator.assert_hitresults(attributes);
+        let n_objects = inner.n300 + inner.n100 + inner.n50;
 
         assert_eq!(
             total_objects, n_objects,
@@ -850,4 +4127,36 @@ mod test {
             total_objects, n_objects
         );
     }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn osu_trace_matches_pp() {
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+
+        let (attributes, trace) = OsuPP::new(&map)
+            .mods(8 + 64) // HDDT
+            .combo(500)
+            .misses(2)
+            .accuracy(97.0)
+            .calculate_with_trace();
+
+        assert_eq!(trace.pp, attributes.pp);
+        assert_eq!(trace.aim.scaled, attributes.pp_aim);
+        assert_eq!(trace.speed.scaled, attributes.pp_speed);
+        assert_eq!(trace.acc.scaled, attributes.pp_acc);
+        assert_eq!(trace.flashlight.scaled, attributes.pp_flashlight);
+
+        let aggregated = trace.aggregated
+            * trace.multiplier
+            * trace.map_multiplier
+            * trace.quality_multiplier
+            * trace.global_multiplier;
+
+        assert!(
+            (aggregated - attributes.pp).abs() < f64::EPSILON,
+            "Expected: {} | Actual: {}",
+            attributes.pp,
+            aggregated
+        );
+    }
 }
@@ -0,0 +1,53 @@
+/// Intermediate values of a single skill's pp value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SkillTrace {
+    /// The base value derived from the difficulty attributes.
+    pub raw: f64,
+    /// The value after applying the length bonus and miss penalties.
+    pub penalized: f64,
+    /// The final value after applying all remaining bonuses and scalings.
+    pub scaled: f64,
+}
+
+/// Labeled intermediate values of an osu!standard performance calculation.
+///
+/// Obtained through [`OsuPP::calculate_with_trace`](crate::osu::OsuPP::calculate_with_trace).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PpTrace {
+    /// The amount of misses including estimated slider breaks based on the combo.
+    pub effective_misses: f64,
+    /// The multiplier of the NF penalty.
+    pub nf_multiplier: f64,
+    /// The multiplier of the SO penalty.
+    pub so_multiplier: f64,
+    /// The total multiplier that is applied onto the aggregated skill values.
+    pub multiplier: f64,
+    /// Progression of the aim value.
+    pub aim: SkillTrace,
+    /// Progression of the speed value.
+    pub speed: SkillTrace,
+    /// Progression of the accuracy value.
+    pub acc: SkillTrace,
+    /// Progression of the flashlight value.
+    pub flashlight: SkillTrace,
+    /// The factor by which the aim value was depressed due to the RX stream penalty.
+    pub rx_depression_factor: f64,
+    /// The power-mean of all skill values before applying the multiplier.
+    pub aggregated: f64,
+    /// The factor of map specific adjustments on the final pp.
+    pub map_multiplier: f64,
+    /// The map quality factor that was applied on the final pp.
+    pub quality_multiplier: f64,
+    /// The global factor that was applied on the final pp.
+    pub global_multiplier: f64,
+    /// The final performance points.
+    pub pp: f64,
+}
+
+impl PpTrace {
+    /// The product of all multipliers that are applied onto the aggregated skill values.
+    #[inline]
+    pub(crate) fn total_multiplier(&self) -> f64 {
+        self.multiplier * self.map_multiplier * self.quality_multiplier * self.global_multiplier
+    }
+}
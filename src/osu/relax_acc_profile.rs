@@ -0,0 +1,34 @@
+use crate::math;
+
+/// Adjustment of the accuracy value for scores with Relax.
+///
+/// Since Relax takes care of clicking, servers might want to nerf its accuracy value.
+/// The adjusted value is `scalar * acc_value ^ exponent`.
+///
+/// The default profile has an exponent and scalar of `1.0`, i.e. the accuracy value is unchanged.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RelaxAccProfile {
+    /// The exponent that is applied onto the accuracy value.
+    pub exponent: f64,
+    /// The factor that the exponentiated accuracy value is multiplied with.
+    pub scalar: f64,
+}
+
+impl RelaxAccProfile {
+    /// Create a new profile with the given exponent and scalar.
+    #[inline]
+    pub fn new(exponent: f64, scalar: f64) -> Self {
+        Self { exponent, scalar }
+    }
+
+    pub(crate) fn apply(self, acc_value: f64) -> f64 {
+        self.scalar * math::powf(acc_value, self.exponent)
+    }
+}
+
+impl Default for RelaxAccProfile {
+    #[inline]
+    fn default() -> Self {
+        Self::new(1.0, 1.0)
+    }
+}
@@ -0,0 +1,59 @@
+use crate::Mods;
+
+/// Collection of all relevant values of an osu!standard score,
+/// e.g. to render them in a template.
+///
+/// Obtained through [`OsuPP::report`](crate::osu::OsuPP::report).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScoreReport {
+    /// The beatmap id of the map.
+    pub beatmap_id: i32,
+    /// The mods' bit values.
+    pub mods: u32,
+    /// The mods' acronyms, e.g. `"HDDT"`, or `"NM"` if there are no mods.
+    pub mods_str: String,
+    /// The final star rating.
+    pub stars: f64,
+    /// The final performance points.
+    pub pp: f64,
+    /// The aim portion of the final pp.
+    pub pp_aim: f64,
+    /// The speed portion of the final pp.
+    pub pp_speed: f64,
+    /// The accuracy portion of the final pp.
+    pub pp_acc: f64,
+    /// The flashlight portion of the final pp.
+    pub pp_flashlight: f64,
+    /// The accuracy between `0` and `100`.
+    pub accuracy: f64,
+    /// The max combo of the score.
+    pub combo: usize,
+    /// The max combo of the map.
+    pub max_combo: usize,
+    /// Amount of 300s.
+    pub n300: usize,
+    /// Amount of 100s.
+    pub n100: usize,
+    /// Amount of 50s.
+    pub n50: usize,
+    /// Amount of misses.
+    pub n_misses: usize,
+    /// The approach rate after applying mods.
+    pub ar: f64,
+    /// The overall difficulty after applying mods.
+    pub od: f64,
+    /// The circle size after applying mods.
+    pub cs: f64,
+    /// The health drain rate after applying mods.
+    pub hp: f64,
+}
+
+pub(crate) fn mods_str(mods: u32) -> String {
+    let acronyms = mods.to_acronyms();
+
+    if acronyms.is_empty() {
+        "NM".to_owned()
+    } else {
+        acronyms
+    }
+}
@@ -0,0 +1,58 @@
+use std::{error::Error as StdError, fmt};
+
+/// Anything that could be invalid about the score state of an [`OsuPP`](crate::OsuPP).
+///
+/// Returned by [`OsuPP::try_calculate`](crate::OsuPP::try_calculate).
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScoreStateError {
+    /// The hitresults sum up to more than the amount of objects.
+    TooManyHitResults {
+        /// The sum of all hitresults.
+        total: usize,
+        /// The amount of passed objects.
+        n_objects: usize,
+    },
+    /// The combo exceeds the map's max combo.
+    ComboTooHigh {
+        /// The specified combo.
+        combo: usize,
+        /// The max combo of the map.
+        max_combo: usize,
+    },
+    /// The accuracy is not between `0` and `100`.
+    InvalidAccuracy(f64),
+    /// The mods contain mutually exclusive mods, e.g. DT and HT.
+    ///
+    /// See [`Mods::sanitize_mods`](crate::Mods::sanitize_mods).
+    IncompatibleMods(u32),
+    /// The accuracy can't be reached by only adjusting the amount of 300s.
+    ///
+    /// See [`OsuPP::fill_n300_from_accuracy`](crate::OsuPP::fill_n300_from_accuracy).
+    UnreachableAccuracy(f64),
+}
+
+impl fmt::Display for ScoreStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyHitResults { total, n_objects } => write!(
+                f,
+                "{} hitresults exceed the amount of objects ({})",
+                total, n_objects
+            ),
+            Self::ComboTooHigh { combo, max_combo } => write!(
+                f,
+                "combo of {} exceeds the max combo ({})",
+                combo, max_combo
+            ),
+            Self::InvalidAccuracy(acc) => write!(f, "invalid accuracy of {}%", acc),
+            Self::IncompatibleMods(mods) => write!(f, "mods {} are mutually exclusive", mods),
+            Self::UnreachableAccuracy(acc) => write!(
+                f,
+                "accuracy of {}% can't be reached with the given hitresults",
+                acc
+            ),
+        }
+    }
+}
+
+impl StdError for ScoreStateError {}
@@ -0,0 +1,25 @@
+/// The scoring semantics that a score was set with.
+///
+/// The clients differ in how they judge slider tails:
+///
+/// - On stable, dropping a slider tail turns the slider's 300 into a 100.
+///   Slider tails are otherwise not part of the accuracy.
+/// - On lazer, slider tails are judged separately and are worth half of a 300
+///   in the accuracy. Dropping one only breaks the combo and leaves the
+///   hitresult of the slider head as it is.
+///
+/// Defaults to [`ScoringMode::Stable`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScoringMode {
+    /// Accounting of osu!stable.
+    Stable,
+    /// Accounting of osu!lazer.
+    Lazer,
+}
+
+impl Default for ScoringMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Stable
+    }
+}
@@ -210,13 +210,13 @@ impl Skill {
         }
     }
 
-    pub(crate) fn count_difficult_strains(&mut self) -> f64 {
-        let top_strain = self
-            .object_strains
-            .clone()
-            .into_iter()
-            .reduce(f64::max)
-            .unwrap();
+    pub(crate) fn count_difficult_strains(&self) -> f64 {
+        let top_strain = self.object_strains.iter().copied().fold(0.0, f64::max);
+
+        // Not enough objects processed yet
+        if top_strain <= 0.0 {
+            return 0.0;
+        }
 
         self.object_strains
             .iter()
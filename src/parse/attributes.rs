@@ -45,21 +45,7 @@ impl BeatmapAttributes {
         let multiplier = mods.od_ar_hp_multiplier();
 
         // AR
-        let mut ar = (self.ar * multiplier) as f64;
-        let mut ar_ms = if ar <= 5.0 {
-            Self::AR0_MS - Self::AR_MS_STEP_1 * ar
-        } else {
-            Self::AR5_MS - Self::AR_MS_STEP_2 * (ar - 5.0)
-        };
-
-        ar_ms = ar_ms.max(Self::AR10_MS).min(Self::AR0_MS);
-        ar_ms /= clock_rate;
-
-        ar = if ar_ms > Self::AR5_MS {
-            (Self::AR0_MS - ar_ms) / Self::AR_MS_STEP_1
-        } else {
-            5.0 + (Self::AR5_MS - ar_ms) / Self::AR_MS_STEP_2
-        };
+        let ar = Self::clock_rate_ar(self.ar * multiplier, clock_rate);
 
         // OD
         let od = (self.od * multiplier).min(10.0);
@@ -84,4 +70,22 @@ impl BeatmapAttributes {
             clock_rate,
         }
     }
+
+    /// Adjust the AR by its hitwindow with respect to the given clock rate.
+    pub(crate) fn clock_rate_ar(ar: f64, clock_rate: f64) -> f64 {
+        let mut ar_ms = if ar <= 5.0 {
+            Self::AR0_MS - Self::AR_MS_STEP_1 * ar
+        } else {
+            Self::AR5_MS - Self::AR_MS_STEP_2 * (ar - 5.0)
+        };
+
+        ar_ms = ar_ms.max(Self::AR10_MS).min(Self::AR0_MS);
+        ar_ms /= clock_rate;
+
+        if ar_ms > Self::AR5_MS {
+            (Self::AR0_MS - ar_ms) / Self::AR_MS_STEP_1
+        } else {
+            5.0 + (Self::AR5_MS - ar_ms) / Self::AR_MS_STEP_2
+        }
+    }
 }
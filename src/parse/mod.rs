@@ -811,6 +811,21 @@ impl Beatmap {
             None => 0.0,
         }
     }
+
+    /// Return a copy of the map with adjusted difficulty settings.
+    ///
+    /// Unlike mods, the values are not scaled but replace the map's settings entirely
+    /// so that difficulty and performance calculations use them as base values.
+    #[inline]
+    pub fn with_difficulty(&self, ar: f32, od: f32, cs: f32, hp: f32) -> Self {
+        Self {
+            ar,
+            od,
+            cs,
+            hp,
+            ..self.clone()
+        }
+    }
 }
 
 mod slider_parsing {
@@ -1093,6 +1108,31 @@ mod tests {
         });
     }
 
+    #[cfg(not(any(feature = "async_std", feature = "async_tokio")))]
+    #[test]
+    fn with_difficulty() {
+        use crate::{OsuPP, OsuStars};
+
+        let map = Beatmap::from_path("./maps/2785319.osu").expect("failed to parse map");
+        let adjusted = map.with_difficulty(10.5, map.od, map.cs, map.hp);
+
+        assert_eq!(adjusted.ar, 10.5);
+        assert_eq!(adjusted.hit_objects.len(), map.hit_objects.len());
+
+        let regular = OsuPP::new(&map).calculate();
+        let adjusted = OsuPP::new(&adjusted).calculate();
+
+        assert!(adjusted.difficulty.ar > regular.difficulty.ar);
+        assert!(adjusted.pp > regular.pp);
+
+        let smaller_circles = map.with_difficulty(map.ar, map.od, 6.0, map.hp);
+
+        let regular = OsuStars::new(&map).calculate();
+        let smaller_circles = OsuStars::new(&smaller_circles).calculate();
+
+        assert!(smaller_circles.stars > regular.stars);
+    }
+
     fn map_ids() -> Vec<i32> {
         vec![
             2785319, // osu
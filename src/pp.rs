@@ -35,7 +35,7 @@ use crate::{
 ///
 /// println!("PP: {} | Stars: {}", next_result.pp(), next_result.stars());
 /// ```
-#[allow(clippy::upper_case_acronyms)]
+#[allow(clippy::upper_case_acronyms, clippy::large_enum_variant)]
 #[derive(Clone, Debug)]
 pub enum AnyPP<'map> {
     /// osu!catch performance calculator
@@ -345,3 +345,59 @@ impl TaikoAttributeProvider for PerformanceAttributes {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn taiko_only_accuracy() {
+        let map = Beatmap::default();
+        let calculator = TaikoPP::new(&map).n300(100).accuracy(97.5);
+
+        assert!(calculator.n300.is_none());
+        assert!((calculator.acc - 0.975).abs() < f64::EPSILON);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn taiko_missing_objects() {
+        let map = Beatmap::from_path("./maps/1028484.osu").expect("failed to parse map");
+        let attributes = TaikoPP::new(&map).calculate().difficulty;
+
+        let total = map.n_circles as usize;
+        let n100 = 30;
+        let misses = 2;
+
+        let given = TaikoPP::new(&map)
+            .attributes(attributes)
+            .n100(n100)
+            .misses(misses)
+            .calculate();
+
+        let expected = TaikoPP::new(&map)
+            .attributes(attributes)
+            .n300(total - n100 - misses)
+            .n100(n100)
+            .misses(misses)
+            .calculate();
+
+        assert_eq!(given, expected);
+    }
+
+    #[cfg(not(any(feature = "async_tokio", feature = "async_std")))]
+    #[test]
+    fn taiko_reuse_attributes() {
+        let map = Beatmap::from_path("./maps/1028484.osu").expect("failed to parse map");
+        let mods = 64;
+
+        let regular = TaikoPP::new(&map).mods(mods).accuracy(98.0).calculate();
+        let reused = TaikoPP::new(&map)
+            .attributes(regular.clone())
+            .mods(mods)
+            .accuracy(98.0)
+            .calculate();
+
+        assert_eq!(regular, reused);
+    }
+}